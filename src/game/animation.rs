@@ -1,7 +1,12 @@
-use bevy::core::Stopwatch;
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 use bevy::utils::Duration;
+use serde::Deserialize;
 
+use crate::game::assets::GameAssets;
+use crate::game::mixer::SoundEffect;
+use crate::game::music::MusicTracks;
 use crate::game::GameState;
 
 pub struct AnimationPlugin;
@@ -14,150 +19,251 @@ impl Plugin for AnimationPlugin {
         )
         .add_system_set(
             SystemSet::on_update(GameState::GameOver)
-                .with_system(animate_sprites.system().label("animate_sprites")),
-        )
-        .add_system(
-            despawn_finished_animations
-                .system()
-                .after("animate_sprites"),
+                .with_system(animate_sprites.system().label("animate_sprites"))
+                .with_system(finish_game_over.system().after("animate_sprites")),
         );
     }
 }
 
 #[derive(Debug)]
-pub struct AnimationTimeLimit {
-    duration: Duration,
-}
-
-#[derive(Debug)]
-pub struct AnimationTimer {
-    elapsed: Option<Stopwatch>,
+pub struct Animation {
+    clip: String,
+    clips: HashMap<String, AnimationClip>,
+    finished: bool,
+    forward: bool,
+    frame: usize,
     timer: Timer,
 }
 
-impl AnimationTimer {
-    /// Create a new animation timer.
-    pub fn new(seconds: f32) -> Self {
+impl Animation {
+    /// Create an animation starting on a clip.
+    pub fn new(name: &str, clip: AnimationClip) -> Self {
+        let timer = Timer::from_seconds(clip.duration(0), false);
+        let mut clips = HashMap::new();
+        clips.insert(name.to_string(), clip);
+
         Self {
-            elapsed: None,
-            timer: Timer::from_seconds(seconds, true),
+            clip: name.to_string(),
+            clips,
+            finished: false,
+            forward: true,
+            frame: 0,
+            timer,
+        }
+    }
+
+    /// Add another clip that can be switched to.
+    pub fn with_clip(mut self, name: &str, clip: AnimationClip) -> Self {
+        self.clips.insert(name.to_string(), clip);
+        self
+    }
+
+    /// Get the name of the clip currently playing.
+    pub fn current(&self) -> &str {
+        &self.clip
+    }
+
+    /// Check if a clip with a given name exists.
+    pub fn has_clip(&self, name: &str) -> bool {
+        self.clips.contains_key(name)
+    }
+
+    /// Get the atlas index of the current frame.
+    pub fn index(&self) -> u32 {
+        self.clips[&self.clip].frames.0 + self.frame as u32
+    }
+
+    /// Check if a one-shot clip has played to the end.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Switch to a clip from its first frame, unless already playing it.
+    pub fn play(&mut self, name: &str) {
+        if (self.clip == name && !self.finished) || !self.clips.contains_key(name) {
+            return;
         }
+
+        self.clip = name.to_string();
+        self.finished = false;
+        self.forward = true;
+        self.frame = 0;
+        self.timer = Timer::from_seconds(self.clips[name].duration(0), false);
     }
 
-    /// Create a new animation timer with total elapsed time.
-    pub fn with_elapsed(seconds: f32) -> Self {
+    /// Step through frames, returning the finish action once a one-shot clip ends.
+    fn advance(&mut self, delta: Duration) -> Option<OnFinish> {
+        if self.finished {
+            return None;
+        }
+
+        self.timer.tick(delta);
+        if !self.timer.finished() {
+            return None;
+        }
+
+        let clip = &self.clips[&self.clip];
+        let last = clip.len() - 1;
+        match clip.mode {
+            PlayMode::Loop => {
+                self.frame = if self.frame >= last {
+                    0
+                } else {
+                    self.frame + 1
+                };
+            }
+            PlayMode::Once => {
+                if self.frame >= last {
+                    self.finished = true;
+                    return Some(clip.on_finish.clone());
+                }
+                self.frame += 1;
+            }
+            PlayMode::PingPong => {
+                // Turn around at either end.
+                if last > 0 {
+                    if (self.forward && self.frame >= last) || (!self.forward && self.frame == 0) {
+                        self.forward = !self.forward;
+                    }
+                    self.frame = if self.forward {
+                        self.frame + 1
+                    } else {
+                        self.frame - 1
+                    };
+                }
+            }
+        }
+
+        self.timer = Timer::from_seconds(clip.duration(self.frame), false);
+        None
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AnimationClip {
+    pub durations: Vec<f32>,
+    pub frames: (u32, u32),
+    pub mode: PlayMode,
+    #[serde(default)]
+    pub on_finish: OnFinish,
+}
+
+impl AnimationClip {
+    /// Loop through a range of frames at a steady rate.
+    pub fn looping(first: u32, last: u32, seconds: f32) -> Self {
         Self {
-            elapsed: Some(Stopwatch::new()),
-            timer: Timer::from_seconds(seconds, true),
+            durations: vec![seconds],
+            frames: (first, last),
+            mode: PlayMode::Loop,
+            on_finish: OnFinish::Hold,
         }
     }
 
-    /// Check whether elapsed time exceeds a given amount.
-    pub fn elapsed(&self, duration: Duration) -> bool {
-        match &self.elapsed {
-            Some(elapsed) => elapsed.elapsed() >= duration,
-            None => false,
+    /// Play through a range of frames once at a steady rate.
+    pub fn once(first: u32, last: u32, seconds: f32, on_finish: OnFinish) -> Self {
+        Self {
+            durations: vec![seconds],
+            frames: (first, last),
+            mode: PlayMode::Once,
+            on_finish,
         }
     }
 
-    /// Check if the timer is finished.
-    pub fn finished(&self) -> bool {
-        self.timer.finished()
+    /// Get how long a frame is shown, reusing the last duration for any extra frames.
+    fn duration(&self, frame: usize) -> f32 {
+        self.durations
+            .get(frame)
+            .or_else(|| self.durations.last())
+            .copied()
+            .unwrap_or(0.1)
     }
 
-    /// Tick the timer.
-    pub fn tick(&mut self, delta: Duration) {
-        self.elapsed.as_mut().map(|e| {
-            e.tick(delta);
-            e
-        });
-        self.timer.tick(delta);
+    /// Get the number of frames in the clip.
+    fn len(&self) -> usize {
+        (self.frames.1.saturating_sub(self.frames.0) + 1) as usize
     }
 }
 
 #[derive(Bundle)]
 pub struct ExplosionBundle {
+    animation: Animation,
     #[bundle]
     sprite: SpriteSheetBundle,
-    time_limit: AnimationTimeLimit,
-    timer: AnimationTimer,
 }
 
 #[derive(Debug)]
 pub struct GameOverAnimation;
 
+#[derive(Clone, Debug, Deserialize)]
+pub enum OnFinish {
+    Despawn,
+    Hold,
+    Switch(String),
+}
+
+impl Default for OnFinish {
+    fn default() -> Self {
+        Self::Hold
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum PlayMode {
+    Loop,
+    Once,
+    PingPong,
+}
+
 pub fn spawn_explosion(
-    server: &AssetServer,
-    audio: &Audio,
-    atlases: &mut Assets<TextureAtlas>,
+    assets: &GameAssets,
+    sounds: &mut EventWriter<SoundEffect>,
     transform: Transform,
 ) -> ExplosionBundle {
     // Play audio.
-    let sound = server.load("sounds/explosion.wav");
-    audio.play(sound);
-
-    // Get texture atlas handle.
-    let texture_atlas = {
-        let asset = server.load("textures/explosion.png");
-        let atlas = TextureAtlas::from_grid(asset, Vec2::new(96.0, 96.0), 12, 1);
-        atlases.add(atlas)
-    };
-
-    // Set up animation timer.
-    let timer = AnimationTimer::with_elapsed(0.1);
-    let time_limit = {
-        let atlas = atlases.get(&texture_atlas).unwrap();
-        let frames = atlas.textures.len();
-        let duration = timer.timer.duration() * frames as u32;
-        AnimationTimeLimit { duration }
-    };
+    sounds.send(SoundEffect::Explosion);
 
     ExplosionBundle {
+        animation: Animation::new(
+            "explode",
+            AnimationClip::once(0, 11, 0.1, OnFinish::Despawn),
+        ),
         sprite: SpriteSheetBundle {
-            texture_atlas,
+            texture_atlas: assets.explosion.clone(),
             transform,
             ..Default::default()
         },
-        time_limit,
-        timer,
     }
 }
 
 fn animate_sprites(
-    atlases: Res<Assets<TextureAtlas>>,
+    mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(
-        &mut AnimationTimer,
-        &Handle<TextureAtlas>,
-        &mut TextureAtlasSprite,
-    )>,
+    mut query: Query<(Entity, &mut Animation, &mut TextureAtlasSprite)>,
 ) {
-    for (mut timer, handle, mut sprite) in query.iter_mut() {
-        timer.tick(time.delta());
-        if timer.finished() {
-            let atlas = atlases.get(handle).unwrap();
-            sprite.index = ((sprite.index as usize + 1) % atlas.textures.len()) as u32;
+    for (entity, mut animation, mut sprite) in query.iter_mut() {
+        let action = animation.advance(time.delta());
+        match action {
+            Some(OnFinish::Despawn) => commands.entity(entity).despawn(),
+            Some(OnFinish::Switch(name)) => animation.play(&name),
+            Some(OnFinish::Hold) | None => {}
         }
+
+        sprite.index = animation.index();
     }
 }
 
-fn despawn_finished_animations(
-    mut commands: Commands,
+fn finish_game_over(
+    time: Res<Time>,
+    tracks: Res<MusicTracks>,
     mut state: ResMut<State<GameState>>,
-    query: Query<(
-        Entity,
-        &AnimationTimeLimit,
-        &AnimationTimer,
-        Option<&GameOverAnimation>,
-    )>,
+    mut waited: Local<f32>,
+    query: Query<&Animation, With<GameOverAnimation>>,
 ) {
-    for (entity, time_limit, timer, game_over_animation) in query.iter() {
-        // Despawn if animation is finished.
-        if timer.elapsed(time_limit.duration) {
-            commands.entity(entity).despawn();
-            if game_over_animation.is_some() {
-                state.set(GameState::Playing).unwrap();
-            }
-        }
+    // Restart once the final explosion and the game over track have played out. The explosion
+    // despawns when it finishes, so an empty query counts as finished too.
+    *waited += time.delta_seconds();
+    if *waited >= tracks.game_over_hold && query.iter().all(|animation| animation.is_finished()) {
+        *waited = 0.0;
+        state.set(GameState::Playing).unwrap();
     }
 }
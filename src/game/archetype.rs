@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use rand::prelude::*;
+use serde::Deserialize;
+
+use crate::game::animation::{Animation, AnimationClip};
+use crate::game::armor::{Defenses, WeakPointEffect, WeakPointSpec};
+use crate::game::assets::GameAssets;
+use crate::game::behavior::{Behavior, PhaseState};
+use crate::game::bullet::FireRate;
+use crate::game::collision::{self, DespawnOutside, Hitbox, SpriteSize};
+use crate::game::enemy::{
+    Attack, Boss, CancelBehavior, ContactDamage, DeathBehavior, Enemy, EnemyBundle, Health,
+    LastHitBy, Movement, ScoreValue,
+};
+use crate::game::feedback::HitFlash;
+use crate::game::item::Item;
+use crate::game::physics::Velocity;
+use crate::game::{SpriteScale, WindowSize};
+
+pub struct ArchetypePlugin;
+
+impl Plugin for ArchetypePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<EnemyArchetype>()
+            .init_asset_loader::<EnemyArchetypeLoader>();
+    }
+}
+
+#[derive(Debug)]
+pub struct Drops(pub Vec<ItemDrop>);
+
+impl Drops {
+    /// Roll the drop table, returning every item to spawn.
+    pub fn roll(&self) -> Vec<Item> {
+        let mut rng = rand::thread_rng();
+        let mut items = Vec::new();
+        for drop in self.0.iter() {
+            if rng.gen::<f32>() < drop.chance {
+                let (min, max) = drop.count;
+                let count = rng.gen_range(min..=max);
+                items.extend((0..count).map(|_| drop.item));
+            }
+        }
+
+        items
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, TypeUuid)]
+#[uuid = "3f0cfe14-434e-40dd-8fe4-e2eed969ed8f"]
+pub struct EnemyArchetype {
+    #[serde(default)]
+    pub animations: HashMap<String, AnimationClip>,
+    #[serde(default)]
+    pub armor: u32,
+    pub attack: Attack,
+    #[serde(default)]
+    pub behavior: Behavior,
+    #[serde(default)]
+    pub boss: bool,
+    pub cancel_behavior: CancelBehavior,
+    #[serde(default)]
+    pub contact_damage: u32,
+    pub death_behavior: DeathBehavior,
+    pub drops: Vec<ItemDrop>,
+    pub fire_rate: FireInterval,
+    pub frame_size: (f32, f32),
+    pub grid: (usize, usize),
+    pub health: u32,
+    pub hitbox: f32,
+    pub movement: Movement,
+    pub score: u32,
+    #[serde(default)]
+    pub shield: Option<f32>,
+    pub speed: (f32, f32),
+    pub texture: String,
+    #[serde(default)]
+    pub weak_points: Vec<WeakPointSpec>,
+}
+
+impl EnemyArchetype {
+    /// Spawn a single enemy of this archetype.
+    pub fn spawn(
+        &self,
+        server: &AssetServer,
+        assets: &mut GameAssets,
+        scale: &SpriteScale,
+        atlases: &mut Assets<TextureAtlas>,
+        window: &WindowSize,
+    ) -> EnemyBundle {
+        let mut rng = rand::thread_rng();
+
+        // Get texture atlas handle, shared between enemies of the same texture.
+        let texture_atlas =
+            assets.atlas(server, atlases, &self.texture, self.frame_size, self.grid);
+
+        // Loop over every frame unless an idle clip is given.
+        let animation = {
+            let (columns, rows) = self.grid;
+            let idle = self
+                .animations
+                .get("idle")
+                .cloned()
+                .unwrap_or_else(|| AnimationClip::looping(0, (columns * rows) as u32 - 1, 0.1));
+            self.animations
+                .iter()
+                .fold(Animation::new("idle", idle), |animation, (name, clip)| {
+                    animation.with_clip(name, clip.clone())
+                })
+        };
+
+        // Get texture dimensions.
+        let sprite_size = {
+            let (width, height) = self.frame_size;
+            SpriteSize::new(width, height, scale.scale)
+        };
+
+        // Calculate transform.
+        let transform = {
+            let width = collision::inner_bound(window.width, sprite_size.width);
+            let height = collision::outer_bound(window.height, sprite_size.height);
+
+            // Spawn in the middle when the sprite is too wide to fit anywhere else.
+            let x = if width > 0.0 {
+                rng.gen_range(-width..width)
+            } else {
+                0.0
+            };
+            scale.xyz(x, height, 2.0)
+        };
+
+        // Calculate velocity.
+        let speed = {
+            let (min, max) = self.speed;
+            rng.gen_range(min..=max)
+        };
+        let velocity = Velocity(self.movement.velocity(speed));
+
+        // Guard the core until every protecting weak point is destroyed.
+        let guards = self
+            .weak_points
+            .iter()
+            .filter(|w| w.effect == WeakPointEffect::ExposeCore)
+            .count() as u32;
+
+        EnemyBundle {
+            animation,
+            attack: self.attack,
+            behavior: self.behavior,
+            boss: Boss(self.boss),
+            cancel_behavior: self.cancel_behavior,
+            contact_damage: ContactDamage(self.contact_damage),
+            death_behavior: self.death_behavior,
+            defenses: Defenses {
+                armor: self.armor,
+                guards,
+                shield: self.shield,
+            },
+            despawn_outside: DespawnOutside,
+            drops: Drops(self.drops.clone()),
+            enemy: Enemy,
+            fire_rate: self.fire_rate.fire_rate(),
+            health: Health::new(self.health),
+            hit_flash: HitFlash::new(0.08),
+            hitbox: Hitbox {
+                radius: self.hitbox,
+            },
+            last_hit_by: LastHitBy(None),
+            movement: self.movement.clone(),
+            phase_state: PhaseState::new(speed),
+            score_value: ScoreValue(self.score),
+            sprite: SpriteSheetBundle {
+                texture_atlas,
+                transform,
+                ..Default::default()
+            },
+            sprite_size,
+            velocity,
+        }
+    }
+
+    /// Get the paths of every texture this enemy and its weak points use.
+    pub fn textures(&self) -> Vec<&str> {
+        let mut textures = vec![self.texture.as_str()];
+        textures.extend(self.weak_points.iter().map(|spec| spec.texture.as_str()));
+        textures
+    }
+
+    /// Check that every range is ordered and the sprite and movement can be used.
+    fn validate(&self) -> anyhow::Result<()> {
+        self.movement.validate()?;
+
+        let (columns, rows) = self.grid;
+        anyhow::ensure!(
+            columns > 0 && rows > 0,
+            "grid ({}, {}) has no frames",
+            columns,
+            rows
+        );
+
+        for spec in self.weak_points.iter() {
+            let (width, height) = spec.frame_size;
+            anyhow::ensure!(
+                width > 0.0 && height > 0.0 && width.is_finite() && height.is_finite(),
+                "weak point frame_size ({}, {}) must be positive",
+                width,
+                height
+            );
+        }
+
+        for (name, clip) in self.animations.iter() {
+            let (first, last) = clip.frames;
+            anyhow::ensure!(
+                first <= last && (last as usize) < columns * rows,
+                "animation {} frames ({}, {}) don't fit the {} frame grid",
+                name,
+                first,
+                last,
+                columns * rows
+            );
+        }
+
+        let (width, height) = self.frame_size;
+        anyhow::ensure!(
+            width > 0.0 && height > 0.0 && width.is_finite() && height.is_finite(),
+            "frame_size ({}, {}) must be positive",
+            width,
+            height
+        );
+
+        let (min, max) = self.speed;
+        anyhow::ensure!(min <= max, "speed range ({}, {}) is inverted", min, max);
+
+        if let FireInterval::Regular(min, max) = self.fire_rate {
+            anyhow::ensure!(min <= max, "fire_rate range ({}, {}) is inverted", min, max);
+        }
+
+        for drop in self.drops.iter() {
+            let (min, max) = drop.count;
+            anyhow::ensure!(
+                min <= max,
+                "drop count range ({}, {}) for {:?} is inverted",
+                min,
+                max,
+                drop.item
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct EnemyArchetypeLoader;
+
+impl AssetLoader for EnemyArchetypeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let archetype = ron::de::from_bytes::<EnemyArchetype>(bytes)?;
+            archetype.validate()?;
+            load_context.set_default_asset(LoadedAsset::new(archetype));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemy.ron"]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum FireInterval {
+    Random(f32),
+    Regular(f32, f32),
+}
+
+impl FireInterval {
+    /// Create a fire rate, picking a random interval within range.
+    pub fn fire_rate(self) -> FireRate {
+        match self {
+            Self::Random(chance) => FireRate::Random(chance),
+            Self::Regular(min, max) => {
+                let mut rng = rand::thread_rng();
+                FireRate::from_seconds(rng.gen_range(min..=max))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ItemDrop {
+    pub chance: f32,
+    pub count: (u32, u32),
+    pub item: Item,
+}
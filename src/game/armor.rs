@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::game::animation;
+use crate::game::assets::GameAssets;
+use crate::game::camera::CameraEffect;
+use crate::game::collision::Hitbox;
+use crate::game::enemy::{Attack, Enemy, Health, LastHitBy};
+use crate::game::feedback::HitFlash;
+use crate::game::mixer::SoundEffect;
+use crate::game::particle::{ParticleBurst, ParticleEffect};
+use crate::game::GameState;
+
+pub struct ArmorPlugin;
+
+impl Plugin for ArmorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(destroy_weak_points.system()),
+        );
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Defenses {
+    pub armor: u32,
+    pub guards: u32,
+    pub shield: Option<f32>,
+}
+
+impl Defenses {
+    /// Check if a hit arriving from a point is stopped by the shield.
+    pub fn blocks(&self, transform: &Transform, from: Vec2) -> bool {
+        let arc = match self.shield {
+            Some(arc) => arc,
+            None => return false,
+        };
+
+        // Compare the direction of the hit against the way the sprite faces.
+        let offset = from - transform.translation.truncate();
+        if offset == Vec2::ZERO {
+            return false;
+        }
+        let facing = (transform.rotation * Vec3::new(0.0, -1.0, 0.0)).truncate();
+        offset.normalize().dot(facing) >= (arc.to_radians() / 2.0).cos()
+    }
+
+    /// Reduce incoming damage by armor, ignoring it while the core is guarded.
+    pub fn reduce(&self, damage: u32) -> u32 {
+        if self.guards > 0 {
+            0
+        } else {
+            damage.saturating_sub(self.armor)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WeakPoint {
+    pub effect: WeakPointEffect,
+}
+
+#[derive(Bundle)]
+pub struct WeakPointBundle {
+    pub defenses: Defenses,
+    pub enemy: Enemy,
+    pub health: Health,
+    pub hit_flash: HitFlash,
+    pub hitbox: Hitbox,
+    pub last_hit_by: LastHitBy,
+    #[bundle]
+    pub sprite: SpriteSheetBundle,
+    pub weak_point: WeakPoint,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+pub enum WeakPointEffect {
+    DisableAttack,
+    ExposeCore,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeakPointSpec {
+    pub effect: WeakPointEffect,
+    pub frame_size: (f32, f32),
+    pub health: u32,
+    pub hitbox: f32,
+    pub offset: (f32, f32),
+    pub texture: String,
+}
+
+impl WeakPointSpec {
+    /// Spawn a weak point, positioned relative to its parent enemy.
+    pub fn spawn(
+        &self,
+        server: &AssetServer,
+        assets: &mut GameAssets,
+        atlases: &mut Assets<TextureAtlas>,
+    ) -> WeakPointBundle {
+        // Get texture atlas handle, shared between weak points of the same texture.
+        let texture_atlas = assets.atlas(server, atlases, &self.texture, self.frame_size, (1, 1));
+
+        let (x, y) = self.offset;
+        WeakPointBundle {
+            defenses: Defenses::default(),
+            enemy: Enemy,
+            health: Health::new(self.health),
+            hit_flash: HitFlash::new(0.08),
+            hitbox: Hitbox {
+                radius: self.hitbox,
+            },
+            last_hit_by: LastHitBy(None),
+            sprite: SpriteSheetBundle {
+                texture_atlas,
+                transform: Transform::from_xyz(x, y, 0.1),
+                ..Default::default()
+            },
+            weak_point: WeakPoint {
+                effect: self.effect,
+            },
+        }
+    }
+}
+
+fn destroy_weak_points(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut effects: EventWriter<CameraEffect>,
+    mut bursts: EventWriter<ParticleBurst>,
+    weak_points: Query<(Entity, &GlobalTransform, &Health, &Parent, &WeakPoint), Changed<Health>>,
+    mut owners: Query<(&mut Attack, &mut Defenses), Without<WeakPoint>>,
+) {
+    for (entity, transform, health, parent, weak_point) in weak_points.iter() {
+        // Break off once health reaches zero.
+        if health.current == 0 {
+            commands.entity(entity).despawn_recursive();
+            commands.spawn_bundle(animation::spawn_explosion(
+                &assets,
+                &mut sounds,
+                Transform::from(*transform),
+            ));
+            effects.send(CameraEffect::Shake(0.3));
+            bursts.send(ParticleBurst {
+                count: 8,
+                effect: ParticleEffect::Debris,
+                position: transform.translation.truncate(),
+            });
+
+            // Weaken the enemy the weak point belonged to.
+            if let Ok((mut attack, mut defenses)) = owners.get_mut(parent.0) {
+                match weak_point.effect {
+                    WeakPointEffect::DisableAttack => *attack = Attack::None,
+                    WeakPointEffect::ExposeCore => {
+                        defenses.guards = defenses.guards.saturating_sub(1)
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::iter;
+
+use bevy::prelude::*;
+use bevy::render::texture::{Extent3d, TextureDimension, TextureFormat};
+use bevy_kira_audio::AudioSource;
+
+use crate::game::bullet::{Bullet, BulletModifiers, Modifier};
+use crate::game::item::Item;
+use crate::game::laser::Laser;
+use crate::game::mixer::SoundEffect;
+
+pub struct AssetsPlugin;
+
+impl Plugin for AssetsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, setup.system());
+    }
+}
+
+/// Texture path, frame size bits and grid identifying a cached atlas.
+type AtlasKey = (String, [u32; 2], (usize, usize));
+
+#[derive(Debug)]
+pub struct GameAssets {
+    atlases: HashMap<AtlasKey, Handle<TextureAtlas>>,
+    bullet_modifiers: Handle<BulletModifiers>,
+    bullets: HashMap<Bullet, Handle<ColorMaterial>>,
+    pub drone: Handle<ColorMaterial>,
+    pub explosion: Handle<TextureAtlas>,
+    pub font: Handle<Font>,
+    pub heart: Handle<ColorMaterial>,
+    pub heart_empty: Handle<ColorMaterial>,
+    items: HashMap<Item, Handle<ColorMaterial>>,
+    lasers: HashMap<Laser, (Handle<ColorMaterial>, Handle<ColorMaterial>)>,
+    modifiers: BulletModifiers,
+    pub none: Handle<ColorMaterial>,
+    pub panel: Handle<ColorMaterial>,
+    pub pixel: Handle<TextureAtlas>,
+    pub player: Handle<TextureAtlas>,
+    sounds: HashMap<SoundEffect, Handle<AudioSource>>,
+}
+
+impl GameAssets {
+    /// Get a texture atlas by texture path and layout, creating it the first time it is needed.
+    pub fn atlas(
+        &mut self,
+        server: &AssetServer,
+        atlases: &mut Assets<TextureAtlas>,
+        path: &str,
+        frame_size: (f32, f32),
+        grid: (usize, usize),
+    ) -> Handle<TextureAtlas> {
+        // Floats aren't hashable, so key on their bits instead.
+        let key = (
+            path.to_string(),
+            [frame_size.0.to_bits(), frame_size.1.to_bits()],
+            grid,
+        );
+        self.atlases
+            .entry(key)
+            .or_insert_with(|| {
+                let asset = server.load(path);
+                let (width, height) = frame_size;
+                let (columns, rows) = grid;
+                let atlas = TextureAtlas::from_grid(asset, Vec2::new(width, height), columns, rows);
+                atlases.add(atlas)
+            })
+            .clone()
+    }
+
+    /// Get the material for a bullet.
+    pub fn bullet(&self, bullet: Bullet) -> Handle<ColorMaterial> {
+        self.bullets[&bullet].clone()
+    }
+
+    /// Get handles to every texture, font and sound these assets use.
+    pub fn handles(
+        &self,
+        materials: &Assets<ColorMaterial>,
+        atlases: &Assets<TextureAtlas>,
+    ) -> Vec<HandleUntyped> {
+        let named_materials = [&self.drone, &self.heart, &self.heart_empty];
+        let named_atlases = [&self.explosion, &self.pixel, &self.player];
+
+        let material_textures = self
+            .bullets
+            .values()
+            .chain(self.items.values())
+            .chain(named_materials.iter().copied())
+            .filter_map(|material| materials.get(material)?.texture.clone());
+        let atlas_textures = self
+            .atlases
+            .values()
+            .chain(named_atlases.iter().copied())
+            .filter_map(|atlas| Some(atlases.get(atlas)?.texture.clone()));
+
+        material_textures
+            .chain(atlas_textures)
+            .map(|texture| texture.clone_untyped())
+            .chain(iter::once(self.bullet_modifiers.clone_untyped()))
+            .chain(iter::once(self.font.clone_untyped()))
+            .chain(self.sounds.values().map(|sound| sound.clone_untyped()))
+            .collect()
+    }
+
+    /// Get the material for an item.
+    pub fn item(&self, item: Item) -> Handle<ColorMaterial> {
+        self.items[&item].clone()
+    }
+
+    /// Get the telegraph and beam materials for a laser.
+    pub fn laser(&self, laser: Laser) -> (Handle<ColorMaterial>, Handle<ColorMaterial>) {
+        self.lasers[&laser].clone()
+    }
+
+    /// Get the modifiers applied to every bullet of a type.
+    pub fn modifiers(&self, bullet: Bullet) -> &[Modifier] {
+        self.modifiers.0.get(&bullet).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replace the bullet modifiers, once their file has loaded.
+    pub fn set_modifiers(&mut self, modifiers: BulletModifiers) {
+        self.modifiers = modifiers;
+    }
+
+    /// Get the audio source for a sound effect.
+    pub fn sound(&self, sound: SoundEffect) -> Handle<AudioSource> {
+        self.sounds[&sound].clone()
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut textures: ResMut<Assets<Texture>>,
+) {
+    let mut texture = |path: &str| {
+        let asset = server.load(path);
+        materials.add(asset.into())
+    };
+
+    let bullets = [
+        Bullet::Basic,
+        Bullet::Bomb,
+        Bullet::Burst,
+        Bullet::Charge,
+        Bullet::Needle,
+        Bullet::Small,
+    ]
+    .iter()
+    .map(|bullet| (*bullet, texture(bullet.texture())))
+    .collect();
+    let items = [Item::Point, Item::Power]
+        .iter()
+        .map(|item| (*item, texture(item.texture())))
+        .collect();
+    let drone = texture("textures/drone.png");
+    let heart = texture("textures/ui/heart.png");
+    let heart_empty = texture("textures/ui/heart_empty.png");
+
+    // The telegraph is a faint copy of the beam.
+    let lasers = [Laser::Focused, Laser::Sweeping]
+        .iter()
+        .map(|laser| {
+            let color = laser.color();
+            let telegraph = Color::rgba(color.r(), color.g(), color.b(), 0.4);
+            (
+                *laser,
+                (materials.add(telegraph.into()), materials.add(color.into())),
+            )
+        })
+        .collect();
+
+    let mut atlas = |path: &str, frame_size: Vec2, columns: usize, rows: usize| {
+        let asset = server.load(path);
+        atlases.add(TextureAtlas::from_grid(asset, frame_size, columns, rows))
+    };
+    let explosion = atlas("textures/explosion.png", Vec2::new(96.0, 96.0), 12, 1);
+    let player = atlas("textures/player.png", Vec2::new(50.0, 43.0), 4, 5);
+
+    // A single white pixel, tinted and scaled by sprites that only need a colored square.
+    let pixel = {
+        let texture = textures.add(Texture::new(
+            Extent3d::new(1, 1, 1),
+            TextureDimension::D2,
+            vec![255; 4],
+            TextureFormat::Rgba8UnormSrgb,
+        ));
+        atlases.add(TextureAtlas::from_grid(texture, Vec2::ONE, 1, 1))
+    };
+
+    let sounds = [
+        SoundEffect::Charge,
+        SoundEffect::Explosion,
+        SoundEffect::Fire,
+        SoundEffect::PlayerHit,
+    ]
+    .iter()
+    .map(|sound| (*sound, server.load(sound.spec().path)))
+    .collect();
+
+    commands.insert_resource(GameAssets {
+        atlases: HashMap::new(),
+        bullet_modifiers: server.load("modifiers.bullet.ron"),
+        bullets,
+        drone,
+        explosion,
+        font: server.load("fonts/DejaVuSansMono.ttf"),
+        heart,
+        heart_empty,
+        items,
+        lasers,
+        modifiers: BulletModifiers::default(),
+        none: materials.add(Color::NONE.into()),
+        panel: materials.add(Color::rgb(0.04, 0.04, 0.08).into()),
+        pixel,
+        player,
+        sounds,
+    });
+}
@@ -0,0 +1,423 @@
+use bevy::prelude::*;
+use bevy::render::texture::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::game::assets::GameAssets;
+use crate::game::camera::MAX_OFFSET;
+use crate::game::collision::outer_bound;
+use crate::game::level::{CurrentLevel, Level};
+use crate::game::particle::lerp_color;
+use crate::game::physics::Velocity;
+use crate::game::starfield::{self, ScrollSpeed, Star, StarfieldConfig};
+use crate::game::{GameState, SpriteScale, WindowSize};
+
+/// Number of color steps in the background gradient.
+const GRADIENT_STEPS: u32 = 64;
+
+/// Seconds taken to blend from one theme into the next.
+const TRANSITION_TIME: f32 = 3.0;
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(ActiveTheme::default())
+            .add_startup_system(setup.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(blend_gradient.system().after("change_theme"))
+                    .with_system(change_theme.system().label("change_theme"))
+                    .with_system(fade_backgrounds.system().after("change_theme"))
+                    .with_system(scroll_tiles.system())
+                    .with_system(wrap_tiles.system()),
+            );
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ActiveTheme {
+    pub level: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BackgroundTheme {
+    pub gradient: (Color, Color),
+    pub starfield: StarfieldConfig,
+    pub tiles: Option<TileLayer>,
+}
+
+impl BackgroundTheme {
+    /// Get the paths of every texture drawn for this theme.
+    pub fn textures(&self) -> Vec<&str> {
+        let mut textures = self.starfield.textures();
+        textures.extend(self.tiles.iter().map(|tiles| tiles.texture.as_str()));
+        textures
+    }
+}
+
+#[derive(Debug)]
+pub struct Fade {
+    color: Color,
+    despawn: bool,
+    from: f32,
+    timer: Timer,
+    to: f32,
+}
+
+impl Fade {
+    /// Fade up from transparent to a color.
+    pub fn fade_in(color: Color, seconds: f32) -> Self {
+        Self {
+            color,
+            despawn: false,
+            from: 0.0,
+            timer: Timer::from_seconds(seconds, false),
+            to: 1.0,
+        }
+    }
+
+    /// Fade down from a color to transparent, then despawn.
+    pub fn fade_out(color: Color, seconds: f32) -> Self {
+        Self {
+            color,
+            despawn: true,
+            from: 1.0,
+            timer: Timer::from_seconds(seconds, false),
+            to: 0.0,
+        }
+    }
+
+    /// Get the color for the current point in the fade.
+    fn color(&self) -> Color {
+        let t = self.from + (self.to - self.from) * self.timer.percent();
+        let mut color = self.color;
+        color.set_a(self.color.a() * t);
+        color
+    }
+}
+
+#[derive(Debug)]
+pub struct Gradient {
+    colors: (Color, Color),
+    from: (Color, Color),
+    texture: Handle<Texture>,
+    timer: Timer,
+}
+
+impl Gradient {
+    /// Start blending from the current colors to new ones.
+    pub fn blend_to(&mut self, colors: (Color, Color), seconds: f32) {
+        self.from = self.current();
+        self.colors = colors;
+        self.timer = Timer::from_seconds(seconds, false);
+    }
+
+    /// Get the top and bottom colors for the current point in the blend.
+    fn current(&self) -> (Color, Color) {
+        let t = if self.timer.finished() {
+            1.0
+        } else {
+            self.timer.percent()
+        };
+        (
+            lerp_color(self.from.0, self.colors.0, t),
+            lerp_color(self.from.1, self.colors.1, t),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Tile {
+    height: f32,
+    speed: f32,
+    wrap: f32,
+}
+
+#[derive(Bundle)]
+pub struct TileBundle {
+    #[bundle]
+    pub sprite: SpriteSheetBundle,
+    pub tile: Tile,
+    pub velocity: Velocity,
+}
+
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub grid: (usize, usize),
+    pub map: Vec<String>,
+    pub speed: f32,
+    pub texture: String,
+    pub tile_size: (f32, f32),
+    pub tint: Color,
+    pub z_index: f32,
+}
+
+/// Fill gradient texture data, from the top color down to the bottom color.
+fn gradient_data(colors: (Color, Color)) -> Vec<u8> {
+    (0..GRADIENT_STEPS)
+        .flat_map(|step| {
+            let t = step as f32 / (GRADIENT_STEPS - 1) as f32;
+            let color = lerp_color(colors.0, colors.1, t);
+            vec![color.r(), color.g(), color.b(), color.a()]
+        })
+        .map(|channel| (channel.max(0.0).min(1.0) * 255.0) as u8)
+        .collect()
+}
+
+/// Spawn a tiled layer, repeating the map until it covers the window.
+pub fn spawn_tiles(
+    commands: &mut Commands,
+    server: &AssetServer,
+    assets: &mut GameAssets,
+    atlases: &mut Assets<TextureAtlas>,
+    layer: &TileLayer,
+    scale: &SpriteScale,
+    window: &WindowSize,
+    fade: Option<f32>,
+) {
+    if layer.map.is_empty() {
+        return;
+    }
+
+    // Get texture atlas handle.
+    let texture_atlas = assets.atlas(server, atlases, &layer.texture, layer.tile_size, layer.grid);
+
+    // Calculate layout.
+    let width = layer.tile_size.0 * scale.scale;
+    let height = layer.tile_size.1 * scale.scale;
+    let columns = layer
+        .map
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    let map_height = layer.map.len() as f32 * height;
+    let copies = (window.height / map_height).ceil() as usize + 1;
+    let wrap = map_height * copies as f32;
+
+    let mut color = layer.tint;
+    if fade.is_some() {
+        color.set_a(0.0);
+    }
+
+    // Rows are written top to bottom, so lay them out from the bottom up.
+    let rows = (0..copies).flat_map(|_| layer.map.iter().rev());
+    for (row_index, row) in rows.enumerate() {
+        let y = -window.height / 2.0 + height / 2.0 + row_index as f32 * height;
+        for (column, tile) in row.chars().enumerate() {
+            let index = match tile_index(tile) {
+                Some(index) => index,
+                None => continue,
+            };
+
+            let x = (column as f32 - (columns as f32 - 1.0) / 2.0) * width;
+            let mut entity = commands.spawn_bundle(TileBundle {
+                sprite: SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        color,
+                        index,
+                        ..Default::default()
+                    },
+                    texture_atlas: texture_atlas.clone(),
+                    transform: scale.xyz(x, y, layer.z_index),
+                    ..Default::default()
+                },
+                tile: Tile {
+                    height,
+                    speed: layer.speed,
+                    wrap,
+                },
+                velocity: Velocity(Vec2::ZERO),
+            });
+            if let Some(seconds) = fade {
+                entity.insert(Fade::fade_in(layer.tint, seconds));
+            }
+        }
+    }
+}
+
+/// Read a tile index from a map character, where `.` or a space is left empty.
+fn tile_index(tile: char) -> Option<u32> {
+    match tile {
+        '.' | ' ' => None,
+        _ => tile.to_digit(36),
+    }
+}
+
+fn blend_gradient(
+    mut textures: ResMut<Assets<Texture>>,
+    time: Res<Time>,
+    mut query: Query<&mut Gradient>,
+) {
+    for mut gradient in query.iter_mut() {
+        if gradient.timer.finished() {
+            continue;
+        }
+
+        gradient.timer.tick(time.delta());
+        if let Some(texture) = textures.get_mut(&gradient.texture) {
+            texture.data = gradient_data(gradient.current());
+        }
+    }
+}
+
+fn change_theme(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut assets: ResMut<GameAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    levels: Res<Vec<Level>>,
+    scale: Res<SpriteScale>,
+    scroll_speed: Res<ScrollSpeed>,
+    window: Res<WindowSize>,
+    mut active: ResMut<ActiveTheme>,
+    mut config: ResMut<StarfieldConfig>,
+    query: Query<&CurrentLevel, Changed<CurrentLevel>>,
+    mut gradients: Query<&mut Gradient>,
+    backgrounds: Query<
+        (
+            Entity,
+            Option<&Handle<ColorMaterial>>,
+            Option<&TextureAtlasSprite>,
+        ),
+        Or<(With<Star>, With<Tile>)>,
+    >,
+) {
+    for current in query.iter() {
+        let index = match current.level {
+            Some(index) if active.level != Some(index) => index,
+            _ => continue,
+        };
+        let theme = &levels[index].theme;
+
+        // Swap straight in for the first level, and blend between later ones.
+        let fade = active.level.map(|_| TRANSITION_TIME);
+        active.level = Some(index);
+
+        for mut gradient in gradients.iter_mut() {
+            gradient.blend_to(theme.gradient, fade.unwrap_or(0.0));
+        }
+
+        // Replace the old stars and tiles.
+        for (entity, material, sprite) in backgrounds.iter() {
+            match fade {
+                Some(seconds) => {
+                    let color = material
+                        .and_then(|material| materials.get(material))
+                        .map(|material| material.color)
+                        .or_else(|| sprite.map(|sprite| sprite.color))
+                        .unwrap_or(Color::WHITE);
+                    commands
+                        .entity(entity)
+                        .insert(Fade::fade_out(color, seconds));
+                }
+                None => commands.entity(entity).despawn(),
+            }
+        }
+
+        *config = theme.starfield.clone();
+        starfield::spawn_starfield(
+            &mut commands,
+            &server,
+            &mut materials,
+            &config,
+            &scroll_speed,
+            &window,
+            fade,
+        );
+        if let Some(tiles) = &theme.tiles {
+            spawn_tiles(
+                &mut commands,
+                &server,
+                &mut assets,
+                &mut atlases,
+                tiles,
+                &scale,
+                &window,
+                fade,
+            );
+        }
+    }
+}
+
+fn fade_backgrounds(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut Fade,
+        Option<&Handle<ColorMaterial>>,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+) {
+    for (entity, mut fade, material, sprite) in query.iter_mut() {
+        fade.timer.tick(time.delta());
+
+        // Stars in a layer share materials, so they fade together.
+        let color = fade.color();
+        if let Some(material) = material.and_then(|material| materials.get_mut(material)) {
+            material.color = color;
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color = color;
+        }
+
+        if fade.timer.finished() {
+            if fade.despawn {
+                commands.entity(entity).despawn();
+            } else {
+                commands.entity(entity).remove::<Fade>();
+            }
+        }
+    }
+}
+
+fn scroll_tiles(scroll_speed: Res<ScrollSpeed>, mut query: Query<(&Tile, &mut Velocity)>) {
+    for (tile, mut velocity) in query.iter_mut() {
+        velocity.0 = Vec2::new(0.0, -tile.speed * scroll_speed.current);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut textures: ResMut<Assets<Texture>>,
+    window: Res<WindowSize>,
+) {
+    // Create a tall, thin texture to stretch over the playfield.
+    let colors = (Color::BLACK, Color::BLACK);
+    let texture = textures.add(Texture::new(
+        Extent3d::new(1, GRADIENT_STEPS, 1),
+        TextureDimension::D2,
+        gradient_data(colors),
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    // Cover the whole playfield, with room to spare while shaking.
+    let size = Vec2::new(window.width, window.height) + Vec2::splat(4.0 * MAX_OFFSET);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(texture.clone().into()),
+            sprite: Sprite::new(size),
+            transform: Transform::from_xyz(0.0, 0.0, -0.05),
+            ..Default::default()
+        })
+        .insert(Gradient {
+            colors,
+            from: colors,
+            texture,
+            timer: Timer::from_seconds(0.0, false),
+        });
+}
+
+fn wrap_tiles(window: Res<WindowSize>, mut query: Query<(&Tile, &mut Transform)>) {
+    for (tile, mut transform) in query.iter_mut() {
+        // Jump back up by a whole number of maps to keep the pattern seamless.
+        let height = outer_bound(window.height, tile.height);
+        if transform.translation.y < -height {
+            transform.translation.y += tile.wrap;
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::game::collision::{self, SpriteSize};
+use crate::game::enemy::{CancelBehavior, Enemy};
+use crate::game::physics::Velocity;
+use crate::game::{GameState, WindowSize};
+
+pub struct BehaviorPlugin;
+
+impl Plugin for BehaviorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<PhaseChanged>().add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(
+                update_phases
+                    .system()
+                    .label("update_phases")
+                    .after("follow_paths")
+                    .after("move_enemies")
+                    .before("apply_acceleration"),
+            ),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Behavior {
+    pub attack_time: Option<f32>,
+    pub phase_cancels: PhaseCancels,
+    pub retreat: Retreat,
+    pub retreat_speed: f32,
+    pub stop_height: Option<f32>,
+    pub volleys: Option<u32>,
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self {
+            attack_time: None,
+            phase_cancels: PhaseCancels::default(),
+            retreat: Retreat::Down,
+            retreat_speed: 4.0,
+            stop_height: None,
+            volleys: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EnemyPhase {
+    Attacking,
+    Entering,
+    Retreating,
+}
+
+/// Bullets to cancel when moving into each phase.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PhaseCancels {
+    pub attacking: CancelBehavior,
+    pub retreating: CancelBehavior,
+}
+
+impl PhaseCancels {
+    /// Get which bullets to cancel on entering a phase.
+    pub fn on_enter(self, phase: EnemyPhase) -> CancelBehavior {
+        match phase {
+            EnemyPhase::Attacking => self.attacking,
+            EnemyPhase::Entering => CancelBehavior::None,
+            EnemyPhase::Retreating => self.retreating,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PhaseChanged {
+    pub enemy: Entity,
+    pub phase: EnemyPhase,
+}
+
+#[derive(Debug)]
+pub struct PhaseState {
+    elapsed: f32,
+    phase: EnemyPhase,
+    speed: f32,
+    volleys: u32,
+}
+
+impl PhaseState {
+    /// Start entering the playfield at a given speed.
+    pub fn new(speed: f32) -> Self {
+        Self {
+            elapsed: 0.0,
+            phase: EnemyPhase::Entering,
+            speed,
+            volleys: 0,
+        }
+    }
+
+    /// Check if currently allowed to attack.
+    pub fn is_attacking(&self) -> bool {
+        self.phase == EnemyPhase::Attacking
+    }
+
+    /// Count a volley towards the attack limit.
+    pub fn record_volley(&mut self) {
+        self.volleys += 1;
+    }
+
+    /// Move to a new phase, resetting counters.
+    fn transition(&mut self, phase: EnemyPhase) {
+        self.elapsed = 0.0;
+        self.phase = phase;
+        self.volleys = 0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Retreat {
+    Down,
+    Side,
+    Up,
+}
+
+fn update_phases(
+    time: Res<Time>,
+    window: Res<WindowSize>,
+    mut events: EventWriter<PhaseChanged>,
+    mut query: Query<
+        (
+            Entity,
+            &Behavior,
+            &mut PhaseState,
+            &SpriteSize,
+            &Transform,
+            &mut Velocity,
+        ),
+        With<Enemy>,
+    >,
+) {
+    for (entity, behavior, mut state, sprite, transform, mut velocity) in query.iter_mut() {
+        state.elapsed += time.delta_seconds();
+        let position = transform.translation.truncate();
+
+        // Decide whether to move on to the next phase.
+        let next = match state.phase {
+            EnemyPhase::Entering => match behavior.stop_height {
+                Some(height) => {
+                    // Head for the stopping point at a steady pace.
+                    let target = Vec2::new(position.x, height * window.height / 2.0);
+                    velocity.0 = (target - position).clamp_length_max(state.speed);
+                    if position.distance_squared(target) < 1.0 {
+                        Some(EnemyPhase::Attacking)
+                    } else {
+                        None
+                    }
+                }
+                None => {
+                    // Start attacking once fully on screen.
+                    let top = collision::inner_bound(window.height, sprite.height);
+                    if position.y <= top {
+                        Some(EnemyPhase::Attacking)
+                    } else {
+                        None
+                    }
+                }
+            },
+            EnemyPhase::Attacking => {
+                if behavior.stop_height.is_some() {
+                    velocity.0 = Vec2::ZERO;
+                }
+
+                // Retreat after either attack limit is reached.
+                let timed_out = behavior.attack_time.map_or(false, |t| state.elapsed >= t);
+                let out_of_volleys = behavior.volleys.map_or(false, |v| state.volleys >= v);
+                if timed_out || out_of_volleys {
+                    Some(EnemyPhase::Retreating)
+                } else {
+                    None
+                }
+            }
+            EnemyPhase::Retreating => {
+                // Leave until despawned outside the window.
+                let direction = match behavior.retreat {
+                    Retreat::Down => Vec2::new(0.0, -1.0),
+                    Retreat::Side if position.x < 0.0 => Vec2::new(-1.0, 0.0),
+                    Retreat::Side => Vec2::new(1.0, 0.0),
+                    Retreat::Up => Vec2::new(0.0, 1.0),
+                };
+                velocity.0 = direction * behavior.retreat_speed;
+                None
+            }
+        };
+
+        if let Some(phase) = next {
+            state.transition(phase);
+            events.send(PhaseChanged {
+                enemy: entity,
+                phase,
+            });
+        }
+    }
+}
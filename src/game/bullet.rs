@@ -1,29 +1,52 @@
+use std::collections::HashMap;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
 use bevy::prelude::*;
-use bevy::utils::Duration;
+use bevy::reflect::TypeUuid;
+use bevy::utils::{BoxedFuture, Duration};
 use rand::prelude::*;
+use serde::Deserialize;
 
 use crate::game::animation;
+use crate::game::assets::GameAssets;
 use crate::game::collision::{self, DespawnOutside, Hitbox};
 use crate::game::enemy::EnemyFaction;
+use crate::game::mixer::SoundEffect;
 use crate::game::physics::{Acceleration, Velocity};
+use crate::game::player::PlayerFaction;
 use crate::game::{GameState, SpriteScale, WindowSize};
 
 pub struct BulletPlugin;
 
 impl Plugin for BulletPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system_set(
-            SystemSet::on_update(GameState::Playing)
-                .with_system(floor_behavior.system())
-                .with_system(wall_behavior.system()),
-        );
+        app.add_asset::<BulletModifiers>()
+            .init_asset_loader::<BulletModifiersLoader>()
+            .add_event::<BulletHit>()
+            .add_event::<CancelBullets>()
+            .add_system(load_modifiers.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(apply_modifiers.system().before("apply_acceleration"))
+                    .with_system(floor_behavior.system())
+                    .with_system(
+                        split_on_hit
+                            .system()
+                            .after("collide_with_enemy_bullets")
+                            .after("collide_with_player_bullets"),
+                    )
+                    .with_system(wall_behavior.system()),
+            );
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub enum Bullet {
     Basic,
     Bomb,
+    Burst,
+    Charge,
+    Needle,
     Small,
 }
 
@@ -31,8 +54,7 @@ impl Bullet {
     /// Spawn a group of bullets.
     pub fn spawn(
         self,
-        server: &AssetServer,
-        materials: &mut Assets<ColorMaterial>,
+        assets: &GameAssets,
         scale: &SpriteScale,
         position: Vec2,
         base_velocity: Vec2,
@@ -42,23 +64,15 @@ impl Bullet {
         speed: f32,
         z_index: f32,
     ) -> Vec<BulletBundle> {
-        let url = match self {
-            Self::Basic => "textures/bullets/basic.png",
-            Self::Bomb => "textures/bullets/bomb.png",
-            Self::Small => "textures/bullets/small.png",
-        };
-
-        // Get material handle.
-        let material = {
-            let asset = server.load(url);
-            materials.add(asset.into())
-        };
+        let material = assets.bullet(self);
+        let modifiers = assets.modifiers(self);
 
         angles
             .iter()
             .map(|angle| {
                 self.spawn_single(
                     material.clone(),
+                    modifiers,
                     scale,
                     position,
                     base_velocity,
@@ -71,10 +85,23 @@ impl Bullet {
             .collect()
     }
 
+    /// Get the path of the bullet's texture.
+    pub fn texture(self) -> &'static str {
+        match self {
+            Self::Basic => "textures/bullets/basic.png",
+            Self::Bomb => "textures/bullets/bomb.png",
+            Self::Burst => "textures/bullets/burst.png",
+            Self::Charge => "textures/bullets/charge.png",
+            Self::Needle => "textures/bullets/needle.png",
+            Self::Small => "textures/bullets/small.png",
+        }
+    }
+
     /// Spawn a single bullet.
     fn spawn_single(
         self,
         material: Handle<ColorMaterial>,
+        modifiers: &[Modifier],
         scale: &SpriteScale,
         position: Vec2,
         base_velocity: Vec2,
@@ -86,9 +113,21 @@ impl Bullet {
         let (damage, radius, wall_behavior, floor_behavior) = match self {
             Self::Basic => (1, 3.0, WallBehavior::None, FloorBehavior::None),
             Self::Bomb => (1, 4.0, WallBehavior::Bounce, FloorBehavior::Explode),
+            Self::Burst => (1, 4.0, WallBehavior::None, FloorBehavior::None),
+            Self::Charge => (1, 6.0, WallBehavior::None, FloorBehavior::None),
+            Self::Needle => (1, 2.5, WallBehavior::None, FloorBehavior::None),
             Self::Small => (1, 1.0, WallBehavior::None, FloorBehavior::None),
         };
 
+        // Get piercing from modifiers.
+        let pierce = modifiers
+            .iter()
+            .find_map(|modifier| match modifier {
+                Modifier::Pierce(count) => Some(*count),
+                _ => None,
+            })
+            .unwrap_or(0);
+
         // Calculate velocity.
         let velocity = Velocity({
             let angle = angle.to_radians();
@@ -106,6 +145,8 @@ impl Bullet {
             hitbox: Hitbox {
                 radius: radius * scale.scale,
             },
+            modifiers: Modifiers::new(modifiers.to_vec()),
+            pierce: Pierce::new(pierce),
             sprite: SpriteBundle {
                 material,
                 transform: scale.translate(position.extend(z_index)),
@@ -125,12 +166,63 @@ pub struct BulletBundle {
     pub damage: Damage,
     pub floor_behavior: FloorBehavior,
     pub hitbox: Hitbox,
+    pub modifiers: Modifiers,
+    pub pierce: Pierce,
     #[bundle]
     pub sprite: SpriteBundle,
     pub velocity: Velocity,
     pub wall_behavior: WallBehavior,
 }
 
+#[derive(Debug)]
+pub struct BulletHit {
+    pub bullet: Entity,
+}
+
+/// Modifiers applied to every bullet of each type, loaded from a data file.
+#[derive(Clone, Debug, Default, Deserialize, TypeUuid)]
+#[serde(transparent)]
+#[uuid = "4b15c943-3e52-4093-9ab7-fa234e8ce152"]
+pub struct BulletModifiers(pub HashMap<Bullet, Vec<Modifier>>);
+
+#[derive(Default)]
+pub struct BulletModifiersLoader;
+
+impl AssetLoader for BulletModifiersLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let modifiers = ron::de::from_bytes::<BulletModifiers>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(modifiers));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bullet.ron"]
+    }
+}
+
+#[derive(Debug)]
+pub struct CancelBullets {
+    pub owner: Option<Entity>,
+}
+
+impl CancelBullets {
+    /// Cancel every enemy bullet.
+    pub fn all() -> Self {
+        Self { owner: None }
+    }
+
+    /// Cancel bullets fired by a single entity.
+    pub fn owned_by(owner: Entity) -> Self {
+        Self { owner: Some(owner) }
+    }
+}
+
 #[derive(Debug)]
 pub struct Damage(pub u32);
 
@@ -157,6 +249,13 @@ impl FireRate {
         }
     }
 
+    /// Restart the timer from zero.
+    pub fn reset(&mut self) {
+        if let Self::Regular(timer) = self {
+            timer.reset();
+        }
+    }
+
     /// Tick the timer.
     pub fn tick(&mut self, delta: Duration) {
         if let Self::Regular(timer) = self {
@@ -165,25 +264,235 @@ impl FireRate {
     }
 }
 
+#[derive(Debug)]
+pub struct FiredBy(pub Entity);
+
 #[derive(Debug)]
 pub enum FloorBehavior {
     Explode,
     None,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub enum Modifier {
+    Accelerate {
+        delay: f32,
+        rate: f32,
+        speed: f32,
+    },
+    Pierce(u32),
+    Split {
+        angles: Vec<f32>,
+        bullet: Bullet,
+        speed: f32,
+        trigger: SplitTrigger,
+    },
+    StopResume {
+        delay: f32,
+        pause: f32,
+    },
+}
+
+#[derive(Debug)]
+pub struct Modifiers {
+    age: f32,
+    list: Vec<Modifier>,
+    resume_velocity: Option<Vec2>,
+}
+
+impl Modifiers {
+    /// Create modifiers for a freshly spawned bullet.
+    pub fn new(list: Vec<Modifier>) -> Self {
+        Self {
+            age: 0.0,
+            list,
+            resume_velocity: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Pierce {
+    hits: Vec<Entity>,
+    remaining: u32,
+}
+
+impl Pierce {
+    /// Pass through a number of targets before despawning.
+    pub fn new(remaining: u32) -> Self {
+        Self {
+            hits: Vec::new(),
+            remaining,
+        }
+    }
+
+    /// Check if a target has already been hit.
+    pub fn has_hit(&self, target: Entity) -> bool {
+        self.hits.contains(&target)
+    }
+
+    /// Record a hit, returning whether the bullet should despawn.
+    pub fn hit(&mut self, target: Entity) -> bool {
+        if self.remaining == 0 {
+            return true;
+        }
+
+        self.remaining -= 1;
+        self.hits.push(target);
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum SplitTrigger {
+    After(f32),
+    OnHit,
+}
+
 #[derive(Debug)]
 pub enum WallBehavior {
     Bounce,
     None,
 }
 
+/// Spawn the bullets that a bullet splits into.
+fn spawn_split(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    scale: &SpriteScale,
+    bullet: Bullet,
+    angles: &[f32],
+    speed: f32,
+    transform: &Transform,
+    velocity: Vec2,
+    enemy_faction: Option<&EnemyFaction>,
+    fired_by: Option<&FiredBy>,
+    player_faction: Option<&PlayerFaction>,
+) {
+    // Spread around the current heading, or downwards when stopped.
+    let base_angle = if velocity == Vec2::ZERO {
+        -90.0
+    } else {
+        velocity.y.atan2(velocity.x).to_degrees()
+    };
+
+    for child in bullet.spawn(
+        assets,
+        scale,
+        transform.translation.truncate(),
+        Vec2::ZERO,
+        Vec2::ZERO,
+        base_angle,
+        angles,
+        speed,
+        transform.translation.z,
+    ) {
+        // Inherit factions and owner.
+        let mut child = commands.spawn_bundle(child);
+        if enemy_faction.is_some() {
+            child.insert(EnemyFaction);
+        }
+        if let Some(fired_by) = fired_by {
+            child.insert(FiredBy(fired_by.0));
+        }
+        if player_faction.is_some() {
+            child.insert(PlayerFaction);
+        }
+    }
+}
+
+fn apply_modifiers(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    scale: Res<SpriteScale>,
+    time: Res<Time>,
+    mut query: Query<
+        (
+            Entity,
+            Option<&EnemyFaction>,
+            Option<&FiredBy>,
+            &mut Modifiers,
+            Option<&PlayerFaction>,
+            &Transform,
+            &mut Velocity,
+        ),
+        With<Bullet>,
+    >,
+) {
+    let delta = time.delta_seconds();
+    for (entity, enemy_faction, fired_by, mut modifiers, player_faction, transform, mut velocity) in
+        query.iter_mut()
+    {
+        let Modifiers {
+            age,
+            list,
+            resume_velocity,
+        } = &mut *modifiers;
+        *age += delta;
+
+        for modifier in list.iter() {
+            match modifier {
+                Modifier::Accelerate { delay, rate, speed } => {
+                    // Ease towards the target speed, keeping direction.
+                    let current = velocity.0.length();
+                    if *age < *delay || resume_velocity.is_some() || current == 0.0 {
+                        continue;
+                    }
+
+                    let target = if current < *speed {
+                        (current + rate * delta).min(*speed)
+                    } else {
+                        (current - rate * delta).max(*speed)
+                    };
+                    velocity.0 *= target / current;
+                }
+                Modifier::Pierce(_) => {}
+                Modifier::Split {
+                    angles,
+                    bullet,
+                    speed,
+                    trigger: SplitTrigger::After(seconds),
+                } => {
+                    if *age >= *seconds {
+                        commands.entity(entity).despawn();
+                        spawn_split(
+                            &mut commands,
+                            &assets,
+                            &scale,
+                            *bullet,
+                            angles,
+                            *speed,
+                            transform,
+                            velocity.0,
+                            enemy_faction,
+                            fired_by,
+                            player_faction,
+                        );
+                        break;
+                    }
+                }
+                Modifier::Split { .. } => {}
+                Modifier::StopResume { delay, pause } => {
+                    if *age >= *delay && *age < delay + pause {
+                        // Hold still, remembering the velocity to resume with.
+                        if resume_velocity.is_none() {
+                            *resume_velocity = Some(velocity.0);
+                        }
+                        velocity.0 = Vec2::ZERO;
+                    } else if let Some(resumed) = resume_velocity.take() {
+                        velocity.0 = resumed;
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn floor_behavior(
     mut commands: Commands,
-    server: Res<AssetServer>,
-    audio: Res<Audio>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    assets: Res<GameAssets>,
+    mut sounds: EventWriter<SoundEffect>,
     scale: Res<SpriteScale>,
-    mut atlases: ResMut<Assets<TextureAtlas>>,
     window: Res<WindowSize>,
     query: Query<(Entity, &FloorBehavior, &Sprite, &Transform), With<Bullet>>,
 ) {
@@ -196,16 +505,14 @@ fn floor_behavior(
                 if transform.translation.y < -height {
                     commands.entity(entity).despawn();
                     commands.spawn_bundle(animation::spawn_explosion(
-                        &server,
-                        &audio,
-                        &mut atlases,
+                        &assets,
+                        &mut sounds,
                         *transform,
                     ));
 
                     // Spawn bullets.
                     for bullet in Bullet::Basic.spawn(
-                        &server,
-                        &mut materials,
+                        &assets,
                         &scale,
                         transform.translation.truncate(),
                         Vec2::ZERO,
@@ -224,6 +531,70 @@ fn floor_behavior(
     }
 }
 
+fn load_modifiers(
+    mut assets: ResMut<GameAssets>,
+    modifiers: Res<Assets<BulletModifiers>>,
+    mut events: EventReader<AssetEvent<BulletModifiers>>,
+) {
+    // Pick up the modifiers file once loaded, and again if it is ever reloaded.
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if let Some(loaded) = modifiers.get(handle) {
+            assets.set_modifiers(loaded.clone());
+        }
+    }
+}
+
+fn split_on_hit(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    scale: Res<SpriteScale>,
+    mut events: EventReader<BulletHit>,
+    query: Query<(
+        Option<&EnemyFaction>,
+        Option<&FiredBy>,
+        &Modifiers,
+        Option<&PlayerFaction>,
+        &Transform,
+        &Velocity,
+    )>,
+) {
+    for event in events.iter() {
+        let (enemy_faction, fired_by, modifiers, player_faction, transform, velocity) =
+            match query.get(event.bullet) {
+                Ok(bullet) => bullet,
+                Err(_) => continue,
+            };
+
+        for modifier in modifiers.list.iter() {
+            if let Modifier::Split {
+                angles,
+                bullet,
+                speed,
+                trigger: SplitTrigger::OnHit,
+            } = modifier
+            {
+                spawn_split(
+                    &mut commands,
+                    &assets,
+                    &scale,
+                    *bullet,
+                    angles,
+                    *speed,
+                    transform,
+                    velocity.0,
+                    enemy_faction,
+                    fired_by,
+                    player_faction,
+                );
+            }
+        }
+    }
+}
+
 fn wall_behavior(
     window: Res<WindowSize>,
     mut query: Query<(&Sprite, &mut Transform, &mut Velocity, &WallBehavior), With<Bullet>>,
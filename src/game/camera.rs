@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameState, WindowSize};
+
+/// Furthest the camera moves from its resting position at full trauma.
+pub const MAX_OFFSET: f32 = 12.0;
+
+/// Furthest the camera rolls in radians at full trauma.
+const MAX_ROLL: f32 = 0.03;
+
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.5;
+
+/// Flash alpha lost per second.
+const FLASH_DECAY: f32 = 4.0;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<CameraEffect>()
+            .insert_resource(HitStop::default())
+            .add_startup_system(setup.system())
+            .add_system(apply_camera_effects.system().label("apply_camera_effects"))
+            .add_system(fade_flash.system().after("apply_camera_effects"))
+            .add_system(shake_camera.system().after("apply_camera_effects"))
+            .add_system(toggle_shake.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing).with_system(
+                    start_hit_stop
+                        .system()
+                        .after("apply_camera_effects")
+                        .after("kill_players")
+                        .after("toggle_paused"),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::HitStop).with_system(end_hit_stop.system()),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum CameraEffect {
+    Flash(f32),
+    HitStop(f32),
+    Shake(f32),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub flash_intensity: f32,
+    pub hit_stop_scale: f32,
+    pub shake_enabled: bool,
+    pub shake_intensity: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            flash_intensity: 1.0,
+            hit_stop_scale: 1.0,
+            shake_enabled: true,
+            shake_intensity: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CameraShake {
+    trauma: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct HitStop {
+    remaining: f32,
+}
+
+#[derive(Debug)]
+pub struct ScreenFlash {
+    alpha: f32,
+}
+
+fn apply_camera_effects(
+    settings: Res<CameraSettings>,
+    mut hit_stop: ResMut<HitStop>,
+    mut events: EventReader<CameraEffect>,
+    mut cameras: Query<&mut CameraShake>,
+    mut flashes: Query<&mut ScreenFlash>,
+) {
+    for event in events.iter() {
+        match *event {
+            CameraEffect::Flash(alpha) => {
+                for mut flash in flashes.iter_mut() {
+                    flash.alpha = flash.alpha.max(alpha * settings.flash_intensity).min(1.0);
+                }
+            }
+            CameraEffect::HitStop(seconds) => {
+                hit_stop.remaining = hit_stop.remaining.max(seconds * settings.hit_stop_scale);
+            }
+            CameraEffect::Shake(trauma) => {
+                // Trauma stacks, so repeated hits build up to a stronger shake.
+                for mut shake in cameras.iter_mut() {
+                    shake.trauma = (shake.trauma + trauma).min(1.0);
+                }
+            }
+        }
+    }
+}
+
+fn end_hit_stop(
+    time: Res<Time>,
+    mut state: ResMut<State<GameState>>,
+    mut hit_stop: ResMut<HitStop>,
+) {
+    hit_stop.remaining -= time.delta_seconds();
+    if hit_stop.remaining <= 0.0 {
+        hit_stop.remaining = 0.0;
+        state.pop().unwrap();
+    }
+}
+
+fn fade_flash(
+    time: Res<Time>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(&mut ScreenFlash, &Handle<ColorMaterial>)>,
+) {
+    for (mut flash, material) in query.iter_mut() {
+        if let Some(material) = materials.get_mut(material) {
+            if flash.alpha > 0.0 || material.color.a() > 0.0 {
+                material.color.set_a(flash.alpha);
+            }
+        }
+
+        flash.alpha = (flash.alpha - FLASH_DECAY * time.delta_seconds()).max(0.0);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    window: Res<WindowSize>,
+) {
+    // Cover the whole playfield, with room to spare while shaking.
+    let size = Vec2::new(window.width, window.height) + Vec2::splat(4.0 * MAX_OFFSET);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.0).into()),
+            sprite: Sprite::new(size),
+            transform: Transform::from_xyz(0.0, 0.0, 900.0),
+            ..Default::default()
+        })
+        .insert(ScreenFlash { alpha: 0.0 });
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    mut query: Query<(&mut CameraShake, &mut Transform)>,
+) {
+    let mut rng = rand::thread_rng();
+    for (mut shake, mut transform) in query.iter_mut() {
+        // Scale with the square of trauma so small knocks stay subtle.
+        let amount = if settings.shake_enabled {
+            shake.trauma * shake.trauma * settings.shake_intensity
+        } else {
+            0.0
+        };
+
+        transform.translation.x = MAX_OFFSET * amount * rng.gen_range(-1.0..=1.0);
+        transform.translation.y = MAX_OFFSET * amount * rng.gen_range(-1.0..=1.0);
+        transform.rotation = Quat::from_rotation_z(MAX_ROLL * amount * rng.gen_range(-1.0..=1.0));
+
+        shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.0);
+    }
+}
+
+fn start_hit_stop(mut state: ResMut<State<GameState>>, mut hit_stop: ResMut<HitStop>) {
+    if hit_stop.remaining > 0.0 {
+        // Drop the freeze if another state change, like game over, is queued.
+        if state.push(GameState::HitStop).is_err() {
+            hit_stop.remaining = 0.0;
+        }
+    }
+}
+
+fn toggle_shake(mut settings: ResMut<CameraSettings>, mut keys: ResMut<Input<KeyCode>>) {
+    // Toggle screen shake when F8 is pressed.
+    if keys.just_pressed(KeyCode::F8) {
+        settings.shake_enabled = !settings.shake_enabled;
+        keys.reset(KeyCode::F8);
+    }
+}
@@ -1,8 +1,14 @@
 use bevy::prelude::*;
+use rand::prelude::*;
 
-use crate::game::bullet::{Bullet, Damage};
-use crate::game::enemy::{Enemy, EnemyFaction, Health};
-use crate::game::player::{InvulnTimer, Player, PlayerFaction};
+use crate::game::armor::Defenses;
+use crate::game::bullet::{Bullet, BulletHit, Damage, FiredBy, Pierce};
+use crate::game::camera::CameraEffect;
+use crate::game::enemy::{ContactDamage, Enemy, EnemyFaction, Health, LastHitBy};
+use crate::game::feedback::EnemyHit;
+use crate::game::laser::{Beam, LaserOwner};
+use crate::game::mixer::SoundEffect;
+use crate::game::player::{Downed, InvulnTimer, Player, PlayerFaction};
 use crate::game::starfield::Star;
 use crate::game::{GameState, WindowSize};
 
@@ -12,13 +18,36 @@ impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_system_set(
             SystemSet::on_update(GameState::Playing)
-                .with_system(bound_player.system().after("move_player"))
+                .with_system(
+                    bound_player
+                        .system()
+                        .label("bound_player")
+                        .after("move_player"),
+                )
                 .with_system(
                     collide_with_enemy_bullets
                         .system()
                         .label("collide_with_enemy_bullets"),
                 )
-                .with_system(collide_with_player_bullets.system()),
+                .with_system(
+                    collide_with_enemies
+                        .system()
+                        .label("collide_with_enemies")
+                        .after("collide_with_enemy_bullets"),
+                )
+                .with_system(
+                    collide_with_enemy_lasers
+                        .system()
+                        .label("collide_with_enemy_lasers")
+                        .after("collide_with_enemy_bullets")
+                        .after("update_lasers"),
+                )
+                .with_system(
+                    collide_with_player_bullets
+                        .system()
+                        .label("collide_with_player_bullets"),
+                )
+                .with_system(collide_with_player_lasers.system().after("update_lasers")),
         )
         .add_system(despawn_outside.system())
         .add_system(wrap_stars.system());
@@ -59,6 +88,33 @@ pub fn outer_bound(dimension: f32, sprite: f32) -> f32 {
     (dimension + sprite) / 2.0
 }
 
+/// Get the position of an entity in the world, following its parent if any.
+pub fn world_position(
+    transform: &Transform,
+    parent: Option<&Parent>,
+    parents: &Query<&Transform, With<Children>>,
+) -> Vec2 {
+    match parent.and_then(|p| parents.get(p.0).ok()) {
+        Some(parent_transform) => parent_transform.mul_vec3(transform.translation).truncate(),
+        None => transform.translation.truncate(),
+    }
+}
+
+/// Get the squared distance from a point to a line segment.
+pub fn segment_distance_squared(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    let t = if length_squared > 0.0 {
+        ((point - start).dot(segment) / length_squared)
+            .min(1.0)
+            .max(0.0)
+    } else {
+        0.0
+    };
+
+    point.distance_squared(start + segment * t)
+}
+
 fn bound_player(
     window: Res<WindowSize>,
     mut query: Query<(&SpriteSize, &mut Transform), With<Player>>,
@@ -71,44 +127,124 @@ fn bound_player(
     }
 }
 
+fn collide_with_enemies(
+    mut sounds: EventWriter<SoundEffect>,
+    mut effects: EventWriter<CameraEffect>,
+    mut enemies: Query<(&ContactDamage, &mut Health, &Hitbox, &Transform), With<Enemy>>,
+    mut players: Query<
+        (&mut Health, &Hitbox, &mut InvulnTimer, &Transform),
+        (With<Player>, Without<Downed>, Without<Enemy>),
+    >,
+) {
+    for (mut health, player_hitbox, mut invuln_timer, player_transform) in players.iter_mut() {
+        for (contact_damage, mut enemy_health, hitbox, transform) in enemies.iter_mut() {
+            // Skip enemies that are harmless to touch.
+            if contact_damage.0 == 0 || enemy_health.current == 0 {
+                continue;
+            }
+
+            // Check for collision.
+            let distance = player_transform
+                .translation
+                .truncate()
+                .distance_squared(transform.translation.truncate());
+            let radius_sum = player_hitbox.radius + hitbox.radius;
+            if distance < radius_sum * radius_sum {
+                // Destroy the enemy on impact.
+                enemy_health.current = 0;
+
+                // Check if currently vulnerable.
+                if invuln_timer.finished() {
+                    // Play audio.
+                    sounds.send(SoundEffect::PlayerHit);
+
+                    // Deal damage.
+                    health.damage(contact_damage.0);
+
+                    // Reset invulnerability timer.
+                    invuln_timer.reset();
+
+                    // Knock the camera about.
+                    effects.send(CameraEffect::Shake(0.5));
+                    effects.send(CameraEffect::HitStop(0.06));
+                }
+            }
+        }
+    }
+}
+
 fn collide_with_enemy_bullets(
     mut commands: Commands,
-    server: Res<AssetServer>,
-    audio: Res<Audio>,
-    mut state: ResMut<State<GameState>>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut effects: EventWriter<CameraEffect>,
     time: Res<Time>,
+    mut hit_events: EventWriter<BulletHit>,
     bullets: Query<(Entity, &Damage, &Hitbox, &Transform), (With<Bullet>, With<EnemyFaction>)>,
-    mut player: Query<(&mut Health, &Hitbox, &mut InvulnTimer, &Transform), With<Player>>,
+    mut players: Query<
+        (&mut Health, &Hitbox, &mut InvulnTimer, &Transform),
+        (With<Player>, Without<Downed>),
+    >,
 ) {
-    let (mut health, player_hitbox, mut invuln_timer, player_transform) =
-        player.single_mut().expect("expected a single player");
-
-    // Tick invulnerability timer.
-    invuln_timer.tick(time.delta());
-    for (entity, damage, hitbox, transform) in bullets.iter() {
-        // Check for collision.
-        let distance = player_transform
-            .translation
-            .truncate()
-            .distance_squared(transform.translation.truncate());
-        let radius_sum = player_hitbox.radius + hitbox.radius;
-        if distance < radius_sum * radius_sum {
-            commands.entity(entity).despawn();
+    for (mut health, player_hitbox, mut invuln_timer, player_transform) in players.iter_mut() {
+        // Tick invulnerability timer.
+        invuln_timer.tick(time.delta());
+        for (entity, damage, hitbox, transform) in bullets.iter() {
+            // Check for collision.
+            let distance = player_transform
+                .translation
+                .truncate()
+                .distance_squared(transform.translation.truncate());
+            let radius_sum = player_hitbox.radius + hitbox.radius;
+            if distance < radius_sum * radius_sum {
+                commands.entity(entity).despawn();
+                hit_events.send(BulletHit { bullet: entity });
+
+                // Check if currently vulnerable.
+                if invuln_timer.finished() {
+                    // Play audio.
+                    sounds.send(SoundEffect::PlayerHit);
+
+                    // Deal damage.
+                    health.damage(damage.0);
+
+                    // Reset invulnerability timer.
+                    invuln_timer.reset();
+
+                    // Knock the camera about.
+                    effects.send(CameraEffect::Shake(0.5));
+                    effects.send(CameraEffect::HitStop(0.06));
+                }
+            }
+        }
+    }
+}
 
-            // Check if currently vulnerable.
-            if invuln_timer.finished() {
+fn collide_with_enemy_lasers(
+    mut sounds: EventWriter<SoundEffect>,
+    mut effects: EventWriter<CameraEffect>,
+    lasers: Query<&Beam, With<EnemyFaction>>,
+    mut players: Query<
+        (&mut Health, &Hitbox, &mut InvulnTimer, &Transform),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for (mut health, hitbox, mut invuln_timer, transform) in players.iter_mut() {
+        for beam in lasers.iter() {
+            // Check for collision while currently vulnerable.
+            if beam.hits(transform.translation.truncate(), hitbox.radius) && invuln_timer.finished()
+            {
                 // Play audio.
-                let sound = server.load("sounds/player_hit.wav");
-                audio.play(sound);
+                sounds.send(SoundEffect::PlayerHit);
 
                 // Deal damage.
-                health.damage(damage.0);
-                if health.current == 0 {
-                    state.set(GameState::GameOver).unwrap();
-                }
+                health.damage(beam.damage);
 
                 // Reset invulnerability timer.
                 invuln_timer.reset();
+
+                // Knock the camera about.
+                effects.send(CameraEffect::Shake(0.5));
+                effects.send(CameraEffect::HitStop(0.06));
             }
         }
     }
@@ -116,20 +252,109 @@ fn collide_with_enemy_bullets(
 
 fn collide_with_player_bullets(
     mut commands: Commands,
-    bullets: Query<(Entity, &Damage, &Hitbox, &Transform), (With<Bullet>, With<PlayerFaction>)>,
-    mut enemies: Query<(&mut Health, &Hitbox, &Transform), With<Enemy>>,
+    mut hit_events: EventWriter<BulletHit>,
+    mut enemy_hit_events: EventWriter<EnemyHit>,
+    mut bullets: Query<
+        (
+            Entity,
+            &Damage,
+            Option<&FiredBy>,
+            &Hitbox,
+            &mut Pierce,
+            &Transform,
+        ),
+        (With<Bullet>, With<PlayerFaction>),
+    >,
+    mut enemies: Query<
+        (
+            Entity,
+            &Defenses,
+            &mut Health,
+            &Hitbox,
+            &mut LastHitBy,
+            Option<&Parent>,
+            &Transform,
+        ),
+        With<Enemy>,
+    >,
+    parents: Query<&Transform, With<Children>>,
 ) {
-    for (mut health, enemy_hitbox, enemy_transform) in enemies.iter_mut() {
-        for (entity, damage, hitbox, transform) in bullets.iter() {
+    for (enemy, defenses, mut health, enemy_hitbox, mut last_hit_by, parent, enemy_transform) in
+        enemies.iter_mut()
+    {
+        let position = world_position(enemy_transform, parent, &parents);
+        for (entity, damage, fired_by, hitbox, mut pierce, transform) in bullets.iter_mut() {
+            // Skip enemies that a piercing bullet already passed through.
+            if pierce.has_hit(enemy) {
+                continue;
+            }
+
             // Check for collision.
-            let distance = enemy_transform
-                .translation
-                .truncate()
-                .distance_squared(transform.translation.truncate());
+            let distance = position.distance_squared(transform.translation.truncate());
             let radius_sum = enemy_hitbox.radius + hitbox.radius;
             if distance < radius_sum * radius_sum {
-                commands.entity(entity).despawn();
-                health.damage(damage.0);
+                // Stop bullets that strike the shield.
+                if defenses.blocks(enemy_transform, transform.translation.truncate()) {
+                    commands.entity(entity).despawn();
+                    continue;
+                }
+
+                if pierce.hit(enemy) {
+                    commands.entity(entity).despawn();
+                }
+                hit_events.send(BulletHit { bullet: entity });
+
+                // Deal damage left over after defenses.
+                let dealt = defenses.reduce(damage.0);
+                if dealt > 0 {
+                    health.damage(dealt);
+                    enemy_hit_events.send(EnemyHit {
+                        enemy,
+                        position: transform.translation.truncate(),
+                    });
+                }
+                if let Some(fired_by) = fired_by {
+                    last_hit_by.0 = Some(fired_by.0);
+                }
+            }
+        }
+    }
+}
+
+fn collide_with_player_lasers(
+    mut hit_events: EventWriter<EnemyHit>,
+    lasers: Query<(&Beam, &LaserOwner), With<PlayerFaction>>,
+    mut enemies: Query<
+        (
+            Entity,
+            &Defenses,
+            &mut Health,
+            &Hitbox,
+            &mut LastHitBy,
+            Option<&Parent>,
+            &Transform,
+        ),
+        With<Enemy>,
+    >,
+    parents: Query<&Transform, With<Children>>,
+) {
+    for (beam, owner) in lasers.iter() {
+        // Deal damage at a fixed rate.
+        if !beam.ready() {
+            continue;
+        }
+
+        for (enemy, defenses, mut health, hitbox, mut last_hit_by, parent, transform) in
+            enemies.iter_mut()
+        {
+            let position = world_position(transform, parent, &parents);
+            if beam.hits(position, hitbox.radius) && !defenses.blocks(transform, beam.origin) {
+                let dealt = defenses.reduce(beam.damage);
+                if dealt > 0 {
+                    health.damage(dealt);
+                    hit_events.send(EnemyHit { enemy, position });
+                }
+                last_hit_by.0 = Some(owner.0);
             }
         }
     }
@@ -149,7 +374,7 @@ fn despawn_outside(
             || transform.translation.y > height
             || transform.translation.y < -height
         {
-            commands.entity(entity).despawn();
+            commands.entity(entity).despawn_recursive();
         }
     }
 
@@ -167,9 +392,12 @@ fn despawn_outside(
 }
 
 fn wrap_stars(window: Res<WindowSize>, mut query: Query<(&Sprite, &mut Transform), With<Star>>) {
+    let mut rng = rand::thread_rng();
     for (sprite, mut transform) in query.iter_mut() {
         let height = outer_bound(window.height, sprite.size.y * transform.scale.y);
         if transform.translation.y < -height {
+            // Come back in somewhere new so the pattern doesn't repeat.
+            transform.translation.x = rng.gen_range((-window.width / 2.0)..(window.width / 2.0));
             transform.translation.y = height;
         }
     }
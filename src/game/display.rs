@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use bevy::render::camera::{Camera, CameraProjection, OrthographicProjection};
+use bevy::window::{WindowMode, WindowResized};
+
+use crate::game::camera::CameraShake;
+use crate::game::ui::HudLayout;
+use crate::game::WindowSize;
+
+/// Logical width of the playfield, before scaling to the window.
+pub const PLAYFIELD_WIDTH: f32 = 800.0;
+
+/// Logical height of the playfield, before scaling to the window.
+pub const PLAYFIELD_HEIGHT: f32 = 960.0;
+
+/// Thickness of the bars covering everything outside the playfield.
+const LETTERBOX_SIZE: f32 = 4000.0;
+
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(DisplaySettings::default())
+            .insert_resource(Viewport { scale: 1.0 })
+            .add_startup_system_to_stage(StartupStage::PostStartup, setup.system())
+            .add_system(apply_window_mode.system())
+            .add_system(scale_playfield.system().label("scale_playfield"))
+            .add_system(toggle_display_settings.system());
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DisplaySettings {
+    pub fullscreen: bool,
+    pub integer_scaling: bool,
+}
+
+#[derive(Debug)]
+pub struct Letterbox;
+
+#[derive(Debug)]
+pub struct Viewport {
+    pub scale: f32,
+}
+
+/// Get how many window pixels one playfield unit covers.
+fn playfield_scale(settings: &DisplaySettings, layout: &HudLayout, width: f32, height: f32) -> f32 {
+    // Leave room for any side panels next to the playfield.
+    let screen_width = PLAYFIELD_WIDTH + 2.0 * layout.panel_width;
+    let scale = (width / screen_width).min(height / PLAYFIELD_HEIGHT);
+
+    // Only snap when the window is large enough for at least one whole step.
+    if settings.integer_scaling && scale >= 1.0 {
+        scale.floor()
+    } else {
+        scale
+    }
+}
+
+fn apply_window_mode(settings: Res<DisplaySettings>, mut windows: ResMut<Windows>) {
+    if settings.is_changed() && !settings.is_added() {
+        let window = windows.get_primary_mut().unwrap();
+        window.set_mode(if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        });
+    }
+}
+
+fn scale_playfield(
+    layout: Res<HudLayout>,
+    settings: Res<DisplaySettings>,
+    windows: Res<Windows>,
+    mut viewport: ResMut<Viewport>,
+    mut resized: EventReader<WindowResized>,
+    mut query: Query<(&mut Camera, &mut OrthographicProjection), With<CameraShake>>,
+) {
+    if !layout.is_changed() && !settings.is_changed() && resized.iter().count() == 0 {
+        return;
+    }
+
+    // Skip minimized windows, which have no size to scale to.
+    let window = windows.get_primary().unwrap();
+    if window.width() <= 0.0 || window.height() <= 0.0 {
+        return;
+    }
+
+    let scale = playfield_scale(&settings, &layout, window.width(), window.height());
+    if (viewport.scale - scale).abs() > f32::EPSILON {
+        viewport.scale = scale;
+    }
+
+    for (mut camera, mut projection) in query.iter_mut() {
+        projection.update(window.width(), window.height());
+        projection.scale = 1.0 / scale;
+        camera.projection_matrix = projection.get_projection_matrix();
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    window: Res<WindowSize>,
+    cameras: Query<Entity, With<CameraShake>>,
+) {
+    let material = materials.add(Color::BLACK.into());
+    let offset = Vec2::new(window.width, window.height) / 2.0 + Vec2::splat(LETTERBOX_SIZE / 2.0);
+    let wide = Vec2::new(window.width + 2.0 * LETTERBOX_SIZE, LETTERBOX_SIZE);
+    let tall = Vec2::new(LETTERBOX_SIZE, window.height);
+
+    // Follow the camera, so the bars stay put while the playfield shakes.
+    for camera in cameras.iter() {
+        commands.entity(camera).with_children(|parent| {
+            for (x, y, size) in [
+                (0.0, offset.y, wide),
+                (0.0, -offset.y, wide),
+                (-offset.x, 0.0, tall),
+                (offset.x, 0.0, tall),
+            ]
+            .iter()
+            {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        material: material.clone(),
+                        sprite: Sprite::new(*size),
+                        transform: Transform::from_xyz(*x, *y, -50.0),
+                        ..Default::default()
+                    })
+                    .insert(Letterbox);
+            }
+        });
+    }
+}
+
+fn toggle_display_settings(
+    mut settings: ResMut<DisplaySettings>,
+    mut keys: ResMut<Input<KeyCode>>,
+) {
+    // Toggle fullscreen when F11 is pressed.
+    if keys.just_pressed(KeyCode::F11) {
+        settings.fullscreen = !settings.fullscreen;
+        keys.reset(KeyCode::F11);
+    }
+
+    // Toggle integer scaling when F10 is pressed.
+    if keys.just_pressed(KeyCode::F10) {
+        settings.integer_scaling = !settings.integer_scaling;
+        keys.reset(KeyCode::F10);
+    }
+}
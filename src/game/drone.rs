@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::game::assets::GameAssets;
+use crate::game::bullet::{Bullet, FireRate, FiredBy};
+use crate::game::input::{Action, Controls};
+use crate::game::player::{ChargeMeter, Downed, Player, PlayerFaction, Power, Ship};
+use crate::game::{GameState, SpriteScale};
+
+pub struct DronePlugin;
+
+impl Plugin for DronePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(fire_bullets.system().after("fire_player_bullets"))
+                .with_system(
+                    move_drones
+                        .system()
+                        .label("move_drones")
+                        .after("record_position_history"),
+                )
+                .with_system(
+                    record_position_history
+                        .system()
+                        .label("record_position_history")
+                        .after("bound_player"),
+                )
+                .with_system(sync_drones.system().before("move_drones")),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct Drone {
+    pub index: usize,
+    pub owner: Entity,
+}
+
+#[derive(Bundle)]
+pub struct DroneBundle {
+    pub drone: Drone,
+    #[bundle]
+    pub sprite: SpriteBundle,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Formation {
+    Orbit,
+    Trail,
+}
+
+#[derive(Debug)]
+pub struct PositionHistory {
+    positions: VecDeque<Vec2>,
+    capacity: usize,
+}
+
+impl PositionHistory {
+    /// Create an empty position history.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            positions: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Get a position from a number of frames ago.
+    pub fn get(&self, frames: usize) -> Option<Vec2> {
+        self.positions
+            .get(frames)
+            .or_else(|| self.positions.back())
+            .copied()
+    }
+
+    /// Record a new position.
+    pub fn push(&mut self, position: Vec2) {
+        // Only record when actually moving so drones stay put.
+        if self.positions.front() == Some(&position) {
+            return;
+        }
+
+        if self.positions.len() == self.capacity {
+            self.positions.pop_back();
+        }
+        self.positions.push_front(position);
+    }
+}
+
+fn fire_bullets(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    scale: Res<SpriteScale>,
+    drones: Query<(&Drone, &Transform)>,
+    player: Query<
+        (Option<&ChargeMeter>, &Controls, &FireRate, &Ship),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for (drone, transform) in drones.iter() {
+        // Fire alongside the player, holding fire whenever the ship itself isn't shooting bullets.
+        let (charge_meter, controls, fire_rate, ship) = match player.get(drone.owner) {
+            Ok(player) => player,
+            Err(_) => continue,
+        };
+        let charging = charge_meter.map_or(false, |m| m.is_charging());
+        let firing = match ship {
+            Ship::Fighter | Ship::Striker => controls.pressed(Action::Fire, &keys, &buttons),
+            Ship::Lancer => false,
+        };
+        if !firing || charging || !fire_rate.finished() {
+            continue;
+        }
+
+        // Fan outwards unless focused.
+        let spread = if controls.pressed(Action::Focus, &keys, &buttons) {
+            0.0
+        } else if drone.index % 2 == 0 {
+            -5.0
+        } else {
+            5.0
+        };
+
+        for bullet in Bullet::Small.spawn(
+            &assets,
+            &scale,
+            transform.translation.truncate(),
+            Vec2::ZERO,
+            Vec2::ZERO,
+            90.0 + spread,
+            &[0.0],
+            12.0,
+            1.0,
+        ) {
+            commands
+                .spawn_bundle(bullet)
+                .insert(PlayerFaction)
+                .insert(FiredBy(drone.owner));
+        }
+    }
+}
+
+fn move_drones(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    time: Res<Time>,
+    mut drones: Query<(&Drone, &mut Transform), Without<Player>>,
+    player: Query<(&Controls, &Formation, &PositionHistory, &Power, &Transform), With<Player>>,
+) {
+    for (drone, mut transform) in drones.iter_mut() {
+        let (controls, formation, history, power, player_transform) = match player.get(drone.owner)
+        {
+            Ok(player) => player,
+            Err(_) => continue,
+        };
+        let player_position = player_transform.translation.truncate();
+        let focused = controls.pressed(Action::Focus, &keys, &buttons);
+
+        // Calculate target position.
+        let count = power.level.max(1) as f32;
+        let index = drone.index as f32;
+        let target = if focused {
+            // Line up tightly in front of the ship.
+            let offset = (index - (count - 1.0) / 2.0) * 20.0;
+            player_position + Vec2::new(offset, 36.0)
+        } else {
+            match formation {
+                Formation::Orbit => {
+                    let angle = time.seconds_since_startup() as f32 * 3.0 + index * TAU / count;
+                    player_position + Vec2::new(angle.cos(), angle.sin()) * 56.0
+                }
+                Formation::Trail => history
+                    .get((drone.index + 1) * 10)
+                    .unwrap_or(player_position),
+            }
+        };
+
+        // Ease towards the target.
+        let position = transform.translation.truncate().lerp(target, 0.3);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+fn record_position_history(mut query: Query<(&mut PositionHistory, &Transform), With<Player>>) {
+    for (mut history, transform) in query.iter_mut() {
+        history.push(transform.translation.truncate());
+    }
+}
+
+fn sync_drones(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    scale: Res<SpriteScale>,
+    drones: Query<(Entity, &Drone)>,
+    player: Query<(Entity, &Power, &Transform), (With<Player>, Changed<Power>)>,
+) {
+    for (owner, power, transform) in player.iter() {
+        // Remove drones above the current power level.
+        let count = power.level as usize;
+        let mut existing = vec![false; count];
+        for (entity, drone) in drones.iter().filter(|(_, d)| d.owner == owner) {
+            if drone.index < count {
+                existing[drone.index] = true;
+            } else {
+                commands.entity(entity).despawn();
+            }
+        }
+
+        // Spawn missing drones at the player.
+        for index in (0..count).filter(|i| !existing[*i]) {
+            commands.spawn_bundle(DroneBundle {
+                drone: Drone { index, owner },
+                sprite: SpriteBundle {
+                    material: assets.drone.clone(),
+                    transform: scale.translate(transform.translation.truncate().extend(2.5)),
+                    ..Default::default()
+                },
+            });
+        }
+    }
+}
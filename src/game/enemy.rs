@@ -1,11 +1,30 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
 use bevy::prelude::*;
 use rand::prelude::*;
-
-use crate::game::animation::{self, AnimationTimer};
-use crate::game::bullet::{Bullet, FireRate};
+use serde::Deserialize;
+
+use crate::game::animation::{self, Animation};
+use crate::game::archetype::{Drops, EnemyArchetype, FireInterval, ItemDrop};
+use crate::game::armor::{Defenses, WeakPointEffect, WeakPointSpec};
+use crate::game::assets::GameAssets;
+use crate::game::behavior::{
+    Behavior, EnemyPhase, PhaseCancels, PhaseChanged, PhaseState, Retreat,
+};
+use crate::game::bullet::{Bullet, CancelBullets, FireRate, FiredBy};
+use crate::game::camera::CameraEffect;
 use crate::game::collision::{self, DespawnOutside, Hitbox, SpriteSize};
+use crate::game::feedback::HitFlash;
+use crate::game::item::Item;
+use crate::game::laser::Laser;
 use crate::game::level::{CurrentLevel, EnemiesLeft, Level, SpawnTimer};
-use crate::game::physics::Velocity;
+use crate::game::mixer::SoundEffect;
+use crate::game::particle::{Emitter, ParticleBurst, ParticleEffect};
+use crate::game::path::Path;
+use crate::game::physics::{Acceleration, Velocity};
+use crate::game::player::{Downed, Player, Score};
+use crate::game::starfield::ScrollSpeed;
 use crate::game::{GameState, SpriteScale, WindowSize};
 
 pub struct EnemyPlugin;
@@ -14,168 +33,430 @@ impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_system_set(
             SystemSet::on_update(GameState::Playing)
+                .with_system(aim_turrets.system().before("fire_enemy_bullets"))
+                .with_system(cancel_phase_bullets.system().after("update_phases"))
+                .with_system(dive_at_players.system().after("update_phases"))
                 .with_system(explode_enemies.system())
-                .with_system(fire_bullets.system())
-                .with_system(move_enemies.system())
+                .with_system(
+                    fire_bullets
+                        .system()
+                        .label("fire_enemy_bullets")
+                        .after("reset_fire_rates"),
+                )
+                .with_system(move_enemies.system().label("move_enemies"))
+                .with_system(
+                    reset_fire_rates
+                        .system()
+                        .label("reset_fire_rates")
+                        .after("update_phases"),
+                )
                 .with_system(spawn_enemies.system()),
         );
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Deserialize)]
 pub enum Attack {
+    Aimed,
     Basic,
     Bomb,
+    Burst,
+    Laser,
+    None,
 }
 
 #[derive(Debug)]
-pub enum DeathBehavior {
-    None,
-    Star,
-}
+pub struct Boss(pub bool);
 
 #[derive(Clone, Copy, Debug)]
-pub enum Enemy {
+pub enum BuiltInEnemy {
     Basic,
     Bomber,
+    Carrier,
+    Kamikaze,
+    Lancer,
+    Seeder,
+    Turret,
+    Warden,
 }
 
-impl Enemy {
-    /// Spawn a single enemy.
-    pub fn spawn_single(
-        self,
-        server: &AssetServer,
-        scale: &SpriteScale,
-        atlases: &mut Assets<TextureAtlas>,
-        window: &WindowSize,
-    ) -> EnemyBundle {
-        let mut rng = rand::thread_rng();
-        let (atlas, attack, death_behavior, fire_rate, health, movement, radius, velocity) =
-            match self {
-                Self::Basic => {
-                    // Get texture atlas.
-                    let atlas = {
-                        let asset = server.load("textures/enemies/basic.png");
-                        TextureAtlas::from_grid(asset, Vec2::new(50.0, 43.0), 4, 1)
-                    };
-
-                    // Calculate fire rate.
-                    let fire_rate = {
-                        let seconds = rng.gen_range(0.4..0.55);
-                        FireRate::from_seconds(seconds)
-                    };
-
-                    // Calculate velocity.
-                    let velocity = Velocity({
-                        let speed = rng.gen_range(1.0..2.0);
-                        Vec2::new(0.0, -speed)
-                    });
-
-                    (
-                        atlas,
-                        Attack::Basic,
-                        DeathBehavior::None,
-                        fire_rate,
-                        1,
-                        Movement::Down,
-                        31.0,
-                        velocity,
-                    )
-                }
-                Self::Bomber => {
-                    // Get texture atlas.
-                    let atlas = {
-                        let asset = server.load("textures/enemies/bomber.png");
-                        TextureAtlas::from_grid(asset, Vec2::new(52.0, 31.0), 4, 1)
-                    };
-
-                    // Calculate velocity.
-                    let velocity = Velocity({
-                        let speed = rng.gen_range(1.5..2.0);
-                        let sign = if rng.gen::<f32>() < 0.5 { 1.0 } else { -1.0 };
-                        Vec2::new(speed * sign, -speed / 4.0)
-                    });
-
-                    (
-                        atlas,
-                        Attack::Bomb,
-                        DeathBehavior::Star,
-                        FireRate::Random(0.005),
-                        2,
-                        Movement::Strafe,
-                        31.0,
-                        velocity,
-                    )
-                }
-            };
+impl BuiltInEnemy {
+    /// Look up a built-in enemy by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "basic" => Some(Self::Basic),
+            "bomber" => Some(Self::Bomber),
+            "carrier" => Some(Self::Carrier),
+            "kamikaze" => Some(Self::Kamikaze),
+            "lancer" => Some(Self::Lancer),
+            "seeder" => Some(Self::Seeder),
+            "turret" => Some(Self::Turret),
+            "warden" => Some(Self::Warden),
+            _ => None,
+        }
+    }
 
-        // Get texture atlas handle.
-        let texture_atlas = atlases.add(atlas);
+    /// Get the archetype describing this enemy.
+    pub fn archetype(self) -> EnemyArchetype {
+        match self {
+            Self::Basic => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::Basic,
+                behavior: Behavior::default(),
+                boss: false,
+                cancel_behavior: CancelBehavior::None,
+                contact_damage: 0,
+                death_behavior: DeathBehavior::None,
+                drops: vec![ItemDrop {
+                    chance: 0.5,
+                    count: (1, 2),
+                    item: Item::Point,
+                }],
+                fire_rate: FireInterval::Regular(0.4, 0.55),
+                frame_size: (50.0, 43.0),
+                grid: (4, 1),
+                health: 1,
+                hitbox: 31.0,
+                movement: Movement::Down,
+                score: 100,
+                shield: None,
+                speed: (1.0, 2.0),
+                texture: "textures/enemies/basic.png".to_string(),
+                weak_points: Vec::new(),
+            },
+            Self::Bomber => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::Bomb,
+                behavior: Behavior::default(),
+                boss: false,
+                cancel_behavior: CancelBehavior::Owned,
+                contact_damage: 0,
+                death_behavior: DeathBehavior::Star,
+                drops: vec![ItemDrop {
+                    chance: 0.25,
+                    count: (1, 1),
+                    item: Item::Power,
+                }],
+                fire_rate: FireInterval::Random(0.005),
+                frame_size: (52.0, 31.0),
+                grid: (4, 1),
+                health: 2,
+                hitbox: 31.0,
+                movement: Movement::Strafe,
+                score: 300,
+                shield: None,
+                speed: (1.5, 2.0),
+                texture: "textures/enemies/bomber.png".to_string(),
+                weak_points: Vec::new(),
+            },
+            Self::Carrier => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::Burst,
+                behavior: Behavior {
+                    attack_time: Some(15.0),
+                    phase_cancels: PhaseCancels {
+                        retreating: CancelBehavior::Owned,
+                        ..Default::default()
+                    },
+                    retreat: Retreat::Up,
+                    stop_height: Some(0.55),
+                    ..Default::default()
+                },
+                boss: false,
+                cancel_behavior: CancelBehavior::Owned,
+                contact_damage: 0,
+                death_behavior: DeathBehavior::Star,
+                drops: vec![
+                    ItemDrop {
+                        chance: 1.0,
+                        count: (1, 1),
+                        item: Item::Power,
+                    },
+                    ItemDrop {
+                        chance: 1.0,
+                        count: (3, 5),
+                        item: Item::Point,
+                    },
+                ],
+                fire_rate: FireInterval::Regular(2.0, 2.0),
+                frame_size: (104.0, 62.0),
+                grid: (4, 1),
+                health: 12,
+                hitbox: 28.0,
+                movement: Movement::Down,
+                score: 1500,
+                shield: None,
+                speed: (0.6, 0.8),
+                texture: "textures/enemies/carrier.png".to_string(),
+                weak_points: vec![
+                    WeakPointSpec {
+                        effect: WeakPointEffect::ExposeCore,
+                        frame_size: (24.0, 24.0),
+                        health: 5,
+                        hitbox: 16.0,
+                        offset: (-38.0, 4.0),
+                        texture: "textures/enemies/pod.png".to_string(),
+                    },
+                    WeakPointSpec {
+                        effect: WeakPointEffect::ExposeCore,
+                        frame_size: (24.0, 24.0),
+                        health: 5,
+                        hitbox: 16.0,
+                        offset: (38.0, 4.0),
+                        texture: "textures/enemies/pod.png".to_string(),
+                    },
+                    WeakPointSpec {
+                        effect: WeakPointEffect::DisableAttack,
+                        frame_size: (16.0, 20.0),
+                        health: 4,
+                        hitbox: 12.0,
+                        offset: (0.0, -24.0),
+                        texture: "textures/enemies/cannon.png".to_string(),
+                    },
+                ],
+            },
+            Self::Kamikaze => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::None,
+                behavior: Behavior::default(),
+                boss: false,
+                cancel_behavior: CancelBehavior::None,
+                contact_damage: 2,
+                death_behavior: DeathBehavior::None,
+                drops: vec![ItemDrop {
+                    chance: 0.5,
+                    count: (1, 1),
+                    item: Item::Point,
+                }],
+                fire_rate: FireInterval::Random(0.0),
+                frame_size: (32.0, 36.0),
+                grid: (4, 1),
+                health: 2,
+                hitbox: 20.0,
+                movement: Movement::Dive { acceleration: 0.3 },
+                score: 250,
+                shield: None,
+                speed: (1.0, 1.5),
+                texture: "textures/enemies/kamikaze.png".to_string(),
+                weak_points: Vec::new(),
+            },
+            Self::Lancer => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::Laser,
+                behavior: Behavior {
+                    attack_time: Some(7.0),
+                    retreat: Retreat::Up,
+                    stop_height: Some(0.5),
+                    ..Default::default()
+                },
+                boss: false,
+                cancel_behavior: CancelBehavior::None,
+                contact_damage: 0,
+                death_behavior: DeathBehavior::None,
+                drops: vec![ItemDrop {
+                    chance: 1.0,
+                    count: (1, 3),
+                    item: Item::Point,
+                }],
+                fire_rate: FireInterval::Regular(3.0, 3.0),
+                frame_size: (50.0, 43.0),
+                grid: (4, 1),
+                health: 3,
+                hitbox: 31.0,
+                movement: Movement::Down,
+                score: 300,
+                shield: None,
+                speed: (0.5, 1.0),
+                texture: "textures/enemies/lancer.png".to_string(),
+                weak_points: Vec::new(),
+            },
+            Self::Seeder => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::Burst,
+                behavior: Behavior {
+                    retreat: Retreat::Side,
+                    stop_height: Some(0.4),
+                    volleys: Some(3),
+                    ..Default::default()
+                },
+                boss: false,
+                cancel_behavior: CancelBehavior::Owned,
+                contact_damage: 0,
+                death_behavior: DeathBehavior::None,
+                drops: vec![ItemDrop {
+                    chance: 0.5,
+                    count: (1, 2),
+                    item: Item::Point,
+                }],
+                fire_rate: FireInterval::Regular(2.5, 2.5),
+                frame_size: (50.0, 43.0),
+                grid: (4, 1),
+                health: 2,
+                hitbox: 31.0,
+                movement: Movement::Down,
+                score: 200,
+                shield: None,
+                speed: (0.8, 1.2),
+                texture: "textures/enemies/seeder.png".to_string(),
+                weak_points: Vec::new(),
+            },
+            Self::Turret => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::Aimed,
+                behavior: Behavior::default(),
+                boss: false,
+                cancel_behavior: CancelBehavior::Owned,
+                contact_damage: 0,
+                death_behavior: DeathBehavior::None,
+                drops: vec![ItemDrop {
+                    chance: 1.0,
+                    count: (2, 3),
+                    item: Item::Point,
+                }],
+                fire_rate: FireInterval::Regular(1.2, 1.5),
+                frame_size: (40.0, 40.0),
+                grid: (4, 1),
+                health: 4,
+                hitbox: 24.0,
+                movement: Movement::Scroll,
+                score: 300,
+                shield: None,
+                speed: (2.0, 2.0),
+                texture: "textures/enemies/turret.png".to_string(),
+                weak_points: Vec::new(),
+            },
+            Self::Warden => EnemyArchetype {
+                animations: HashMap::new(),
+                armor: 0,
+                attack: Attack::Basic,
+                behavior: Behavior::default(),
+                boss: false,
+                cancel_behavior: CancelBehavior::None,
+                contact_damage: 0,
+                death_behavior: DeathBehavior::None,
+                drops: vec![ItemDrop {
+                    chance: 0.75,
+                    count: (1, 3),
+                    item: Item::Point,
+                }],
+                fire_rate: FireInterval::Regular(0.8, 1.0),
+                frame_size: (50.0, 43.0),
+                grid: (4, 1),
+                health: 3,
+                hitbox: 31.0,
+                movement: Movement::Strafe,
+                score: 400,
+                shield: Some(90.0),
+                speed: (1.0, 1.5),
+                texture: "textures/enemies/warden.png".to_string(),
+                weak_points: Vec::new(),
+            },
+        }
+    }
+}
 
-        // Get texture dimensions.
-        let sprite_size = {
-            let atlas = atlases.get(&texture_atlas).unwrap();
-            let rect = atlas.textures.first().unwrap();
-            SpriteSize::new(rect.width(), rect.height(), scale.scale)
-        };
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum CancelBehavior {
+    All,
+    None,
+    Owned,
+}
 
-        // Calculate transform.
-        let transform = {
-            let width = collision::inner_bound(window.width, sprite_size.width);
-            let height = collision::outer_bound(window.height, sprite_size.height);
-            scale.xyz(rng.gen_range(-width..width), height, 2.0)
-        };
+impl Default for CancelBehavior {
+    fn default() -> Self {
+        Self::None
+    }
+}
 
-        EnemyBundle {
-            attack,
-            death_behavior,
-            despawn_outside: DespawnOutside,
-            enemy: self,
-            fire_rate,
-            health: Health::new(health),
-            hitbox: Hitbox { radius },
-            movement,
-            sprite: SpriteSheetBundle {
-                texture_atlas,
-                transform,
-                ..Default::default()
-            },
-            sprite_size,
-            timer: AnimationTimer::new(0.1),
-            velocity,
+impl CancelBehavior {
+    /// Get the cancel event to send for an enemy, if any.
+    pub fn event(self, enemy: Entity) -> Option<CancelBullets> {
+        match self {
+            Self::All => Some(CancelBullets::all()),
+            Self::None => None,
+            Self::Owned => Some(CancelBullets::owned_by(enemy)),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum DeathBehavior {
+    None,
+    Star,
+}
+
+#[derive(Debug)]
+pub struct ContactDamage(pub u32);
+
+#[derive(Debug)]
+pub struct Enemy;
+
 #[derive(Bundle)]
 pub struct EnemyBundle {
+    pub animation: Animation,
     pub attack: Attack,
+    pub behavior: Behavior,
+    pub boss: Boss,
+    pub cancel_behavior: CancelBehavior,
+    pub contact_damage: ContactDamage,
     pub death_behavior: DeathBehavior,
+    pub defenses: Defenses,
     pub despawn_outside: DespawnOutside,
+    pub drops: Drops,
     pub enemy: Enemy,
     pub fire_rate: FireRate,
     pub health: Health,
+    pub hit_flash: HitFlash,
     pub hitbox: Hitbox,
+    pub last_hit_by: LastHitBy,
     pub movement: Movement,
+    pub phase_state: PhaseState,
+    pub score_value: ScoreValue,
     #[bundle]
     pub sprite: SpriteSheetBundle,
     pub sprite_size: SpriteSize,
-    pub timer: AnimationTimer,
     pub velocity: Velocity,
 }
 
 #[derive(Debug)]
 pub struct EnemyFaction;
 
+#[derive(Clone, Debug)]
+pub enum EnemyKind {
+    Archetype(Handle<EnemyArchetype>),
+    BuiltIn(BuiltInEnemy),
+}
+
+impl EnemyKind {
+    /// Refer to an enemy by name, loading an archetype file unless built in.
+    pub fn named(server: &AssetServer, name: &str) -> Self {
+        match BuiltInEnemy::from_name(name) {
+            Some(enemy) => Self::BuiltIn(enemy),
+            None => Self::Archetype(server.load(format!("enemies/{}.enemy.ron", name).as_str())),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Health {
     pub current: u32,
+    pub max: u32,
 }
 
 impl Health {
     /// Start at max health.
     pub fn new(amount: u32) -> Self {
-        Self { current: amount }
+        Self {
+            current: amount,
+            max: amount,
+        }
     }
 
     /// Subtract damage from current health.
@@ -185,30 +466,232 @@ impl Health {
 }
 
 #[derive(Debug)]
+pub struct LastHitBy(pub Option<Entity>);
+
+#[derive(Clone, Debug, Deserialize)]
 pub enum Movement {
+    Circle { radius: f32, turns: f32 },
+    Dive { acceleration: f32 },
     Down,
+    Path(Path),
+    Scroll,
+    Sine { amplitude: f32, wavelength: f32 },
     Strafe,
+    Swoop,
+}
+
+impl Movement {
+    /// Get the initial velocity for a given speed.
+    pub fn velocity(&self, speed: f32) -> Vec2 {
+        match self {
+            Self::Circle { .. }
+            | Self::Dive { .. }
+            | Self::Down
+            | Self::Path(_)
+            | Self::Scroll
+            | Self::Sine { .. }
+            | Self::Swoop => Vec2::new(0.0, -speed),
+            Self::Strafe => {
+                // Head towards a random side.
+                let mut rng = rand::thread_rng();
+                let sign = if rng.gen::<f32>() < 0.5 { 1.0 } else { -1.0 };
+                Vec2::new(speed * sign, -speed / 4.0)
+            }
+        }
+    }
+
+    /// Check for settings that would produce a broken route.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Circle { radius, turns } => {
+                anyhow::ensure!(radius.is_finite(), "circle radius must be finite");
+                anyhow::ensure!(
+                    turns.is_finite() && *turns > 0.0,
+                    "circle turns must be positive"
+                );
+            }
+            Self::Path(path) => path.validate()?,
+            Self::Sine {
+                amplitude,
+                wavelength,
+            } => {
+                anyhow::ensure!(amplitude.is_finite(), "sine amplitude must be finite");
+                anyhow::ensure!(
+                    wavelength.is_finite() && *wavelength != 0.0,
+                    "sine wavelength must be non-zero"
+                );
+            }
+            Self::Dive { .. } | Self::Down | Self::Scroll | Self::Strafe | Self::Swoop => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ScoreValue(pub u32);
+
+/// Get the rotation that points a downwards-facing sprite in a direction.
+fn facing_rotation(direction: Vec2) -> Quat {
+    Quat::from_rotation_z(direction.x.atan2(-direction.y))
+}
+
+/// Get the offset to the closest standing player.
+fn closest_player<'a>(
+    position: Vec2,
+    players: impl Iterator<Item = &'a Transform>,
+) -> Option<Vec2> {
+    players
+        .map(|t| t.translation.truncate() - position)
+        .min_by(|a, b| a.length_squared().partial_cmp(&b.length_squared()).unwrap())
+}
+
+fn aim_turrets(
+    time: Res<Time>,
+    mut turrets: Query<(&Attack, &mut Transform), With<Enemy>>,
+    players: Query<&Transform, (With<Player>, Without<Downed>, Without<Enemy>)>,
+) {
+    for (attack, mut transform) in turrets.iter_mut() {
+        if let Attack::Aimed = attack {
+            let offset = match closest_player(transform.translation.truncate(), players.iter()) {
+                Some(offset) if offset != Vec2::ZERO => offset,
+                _ => continue,
+            };
+
+            // Turn towards the player at a limited rate.
+            let facing = transform.rotation * Vec3::new(0.0, -1.0, 0.0);
+            let current = facing.x.atan2(-facing.y);
+            let target = offset.x.atan2(-offset.y);
+            let difference = (target - current + PI).rem_euclid(TAU) - PI;
+            let max_turn = 2.0 * time.delta_seconds();
+            let angle = current + difference.max(-max_turn).min(max_turn);
+            transform.rotation = Quat::from_rotation_z(angle);
+        }
+    }
+}
+
+fn cancel_phase_bullets(
+    mut events: EventReader<PhaseChanged>,
+    mut cancel_events: EventWriter<CancelBullets>,
+    query: Query<&Behavior, With<Enemy>>,
+) {
+    for event in events.iter() {
+        // Convert bullets into items as configured for the new phase.
+        if let Ok(behavior) = query.get(event.enemy) {
+            if let Some(cancel) = behavior
+                .phase_cancels
+                .on_enter(event.phase)
+                .event(event.enemy)
+            {
+                cancel_events.send(cancel);
+            }
+        }
+    }
+}
+
+fn dive_at_players(
+    mut commands: Commands,
+    mut events: EventReader<PhaseChanged>,
+    mut enemies: Query<(&Movement, &mut Transform, &mut Velocity), With<Enemy>>,
+    players: Query<&Transform, (With<Player>, Without<Downed>, Without<Enemy>)>,
+) {
+    for event in events.iter() {
+        if event.phase != EnemyPhase::Attacking {
+            continue;
+        }
+
+        // Lock onto the closest player's current position.
+        if let Ok((Movement::Dive { acceleration }, mut transform, mut velocity)) =
+            enemies.get_mut(event.enemy)
+        {
+            let direction = match closest_player(transform.translation.truncate(), players.iter()) {
+                Some(offset) if offset != Vec2::ZERO => offset.normalize(),
+                _ => Vec2::new(0.0, -1.0),
+            };
+
+            transform.rotation = facing_rotation(direction);
+            velocity.0 = direction * velocity.0.length();
+            commands
+                .entity(event.enemy)
+                .insert(Acceleration(direction * *acceleration));
+        }
+    }
 }
 
 fn explode_enemies(
     mut commands: Commands,
-    server: Res<AssetServer>,
-    audio: Res<Audio>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    assets: Res<GameAssets>,
+    mut sounds: EventWriter<SoundEffect>,
     scale: Res<SpriteScale>,
-    mut atlases: ResMut<Assets<TextureAtlas>>,
-    query: Query<(Entity, &DeathBehavior, &Health, &Transform), (With<Enemy>, Changed<Health>)>,
+    mut cancel_events: EventWriter<CancelBullets>,
+    mut effects: EventWriter<CameraEffect>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut players: Query<&mut Score, With<Player>>,
+    query: Query<
+        (
+            Entity,
+            &Boss,
+            &CancelBehavior,
+            &DeathBehavior,
+            &Drops,
+            &Health,
+            &LastHitBy,
+            &ScoreValue,
+            &Transform,
+        ),
+        (With<Enemy>, Changed<Health>),
+    >,
 ) {
-    for (entity, death_behavior, health, transform) in query.iter() {
+    for (
+        entity,
+        boss,
+        cancel_behavior,
+        death_behavior,
+        drops,
+        health,
+        last_hit_by,
+        score_value,
+        transform,
+    ) in query.iter()
+    {
         // Explode once health reaches zero.
         if health.current == 0 {
-            commands.entity(entity).despawn();
-            commands.spawn_bundle(animation::spawn_explosion(
-                &server,
-                &audio,
-                &mut atlases,
-                *transform,
-            ));
+            commands.entity(entity).despawn_recursive();
+            commands.spawn_bundle(animation::spawn_explosion(&assets, &mut sounds, *transform));
+
+            bursts.send(ParticleBurst {
+                count: 16,
+                effect: ParticleEffect::Debris,
+                position: transform.translation.truncate(),
+            });
+
+            // Shake harder for bosses, freezing briefly.
+            if boss.0 {
+                effects.send(CameraEffect::Shake(0.8));
+                effects.send(CameraEffect::HitStop(0.12));
+                effects.send(CameraEffect::Flash(0.6));
+            } else {
+                effects.send(CameraEffect::Shake(0.2));
+            }
+
+            // Award score to whoever landed the final hit.
+            if let Some(mut score) = last_hit_by.0.and_then(|p| players.get_mut(p).ok()) {
+                score.0 += score_value.0;
+            }
+
+            // Spawn dropped items.
+            for item in drops.roll() {
+                commands.spawn_bundle(item.spawn_single(
+                    &assets,
+                    &scale,
+                    transform.translation.truncate(),
+                ));
+            }
+
+            // Convert remaining bullets into items.
+            if let Some(event) = cancel_behavior.event(entity) {
+                cancel_events.send(event);
+            }
 
             // Execute death behavior.
             let mut rng = rand::thread_rng();
@@ -218,8 +701,7 @@ fn explode_enemies(
                     // Calculate a random base angle.
                     let base_angle = rng.gen_range(0.0..60.0);
                     for bullet in Bullet::Basic.spawn(
-                        &server,
-                        &mut materials,
+                        &assets,
                         &scale,
                         transform.translation.truncate(),
                         Vec2::ZERO,
@@ -239,20 +721,49 @@ fn explode_enemies(
 
 fn fire_bullets(
     mut commands: Commands,
-    server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    assets: Res<GameAssets>,
     scale: Res<SpriteScale>,
     time: Res<Time>,
-    mut query: Query<(&Attack, &mut FireRate, &Transform, &Velocity), With<Enemy>>,
+    mut query: Query<
+        (
+            Entity,
+            &Attack,
+            &mut FireRate,
+            &mut PhaseState,
+            &Transform,
+            &Velocity,
+        ),
+        With<Enemy>,
+    >,
 ) {
-    for (attack, mut fire_rate, transform, velocity) in query.iter_mut() {
+    for (entity, attack, mut fire_rate, mut phase_state, transform, velocity) in query.iter_mut() {
+        // Only fire while attacking.
+        if !phase_state.is_attacking() {
+            continue;
+        }
+
         // Tick fire rate timer.
         fire_rate.tick(time.delta());
         if fire_rate.finished() {
+            phase_state.record_volley();
             let bullets = match attack {
+                Attack::Aimed => {
+                    // Fire wherever the sprite is currently facing.
+                    let facing = transform.rotation * Vec3::new(0.0, -1.0, 0.0);
+                    Bullet::Basic.spawn(
+                        &assets,
+                        &scale,
+                        transform.translation.truncate(),
+                        Vec2::ZERO,
+                        Vec2::ZERO,
+                        facing.y.atan2(facing.x).to_degrees(),
+                        &[0.0],
+                        6.0,
+                        4.0,
+                    )
+                }
                 Attack::Basic => Bullet::Basic.spawn(
-                    &server,
-                    &mut materials,
+                    &assets,
                     &scale,
                     transform.translation.truncate(),
                     Vec2::ZERO,
@@ -268,8 +779,7 @@ fn fire_bullets(
                     base_velocity.y = 0.0;
 
                     Bullet::Bomb.spawn(
-                        &server,
-                        &mut materials,
+                        &assets,
                         &scale,
                         transform.translation.truncate(),
                         base_velocity,
@@ -280,23 +790,65 @@ fn fire_bullets(
                         4.0,
                     )
                 }
+                Attack::Burst => Bullet::Burst.spawn(
+                    &assets,
+                    &scale,
+                    transform.translation.truncate(),
+                    Vec2::ZERO,
+                    Vec2::ZERO,
+                    -90.0,
+                    &[0.0],
+                    4.0,
+                    4.0,
+                ),
+                Attack::Laser => {
+                    // Sweep in a random direction.
+                    let mut rng = rand::thread_rng();
+                    let sweep = if rng.gen::<f32>() < 0.5 { 0.3 } else { -0.3 };
+
+                    commands
+                        .spawn_bundle(Laser::Sweeping.spawn(
+                            &assets,
+                            entity,
+                            transform.translation.truncate(),
+                            -90.0,
+                            sweep,
+                            1.5,
+                        ))
+                        .insert(EnemyFaction);
+                    Vec::new()
+                }
+                Attack::None => Vec::new(),
             };
 
             for bullet in bullets {
-                commands.spawn_bundle(bullet).insert(EnemyFaction);
+                commands
+                    .spawn_bundle(bullet)
+                    .insert(EnemyFaction)
+                    .insert(FiredBy(entity));
             }
         }
     }
 }
 
 fn move_enemies(
+    scroll_speed: Res<ScrollSpeed>,
     window: Res<WindowSize>,
     mut query: Query<(&Movement, &SpriteSize, &mut Transform, &mut Velocity), With<Enemy>>,
 ) {
     let mut rng = rand::thread_rng();
     for (movement, sprite, mut transform, mut velocity) in query.iter_mut() {
         match movement {
-            Movement::Down => {}
+            Movement::Circle { .. }
+            | Movement::Dive { .. }
+            | Movement::Down
+            | Movement::Path(_)
+            | Movement::Sine { .. }
+            | Movement::Swoop => {}
+            Movement::Scroll => {
+                // Stay fixed to the ground as it scrolls past.
+                velocity.0 = Vec2::new(0.0, -scroll_speed.current);
+            }
             Movement::Strafe => {
                 // Change direction when hitting wall or at random.
                 let width = collision::inner_bound(window.width, sprite.width);
@@ -312,12 +864,28 @@ fn move_enemies(
     }
 }
 
+fn reset_fire_rates(
+    mut events: EventReader<PhaseChanged>,
+    mut query: Query<&mut FireRate, With<Enemy>>,
+) {
+    for event in events.iter() {
+        // Give a full interval of warning before the first volley.
+        if event.phase == EnemyPhase::Attacking {
+            if let Ok(mut fire_rate) = query.get_mut(event.enemy) {
+                fire_rate.reset();
+            }
+        }
+    }
+}
+
 fn spawn_enemies(
     mut commands: Commands,
     server: Res<AssetServer>,
+    mut assets: ResMut<GameAssets>,
     levels: Res<Vec<Level>>,
     scale: Res<SpriteScale>,
     mut atlases: ResMut<Assets<TextureAtlas>>,
+    archetypes: Res<Assets<EnemyArchetype>>,
     time: Res<Time>,
     window: Res<WindowSize>,
     mut query: Query<(&mut CurrentLevel, &mut EnemiesLeft, &mut SpawnTimer)>,
@@ -347,13 +915,45 @@ fn spawn_enemies(
     // Tick spawn timer.
     timer.tick(time.delta());
     if timer.finished() {
-        // Decrement enemies left.
-        enemies_left.count -= 1;
-
         // Choose a random enemy to spawn.
         let mut rng = rand::thread_rng();
-        let enemy = level.enemies.choose_weighted(&mut rng, |e| e.1).unwrap().0;
-        commands.spawn_bundle(enemy.spawn_single(&server, &scale, &mut atlases, &window));
+        let kind = &level.enemies.choose_weighted(&mut rng, |e| e.1).unwrap().0;
+
+        // Wait for archetype files to finish loading.
+        let built_in;
+        let archetype = match kind {
+            EnemyKind::Archetype(handle) => match archetypes.get(handle) {
+                Some(archetype) => archetype,
+                None => return,
+            },
+            EnemyKind::BuiltIn(enemy) => {
+                built_in = enemy.archetype();
+                &built_in
+            }
+        };
+
+        // Attach weak points as children so they move with the enemy.
+        let mut enemy = commands.spawn_bundle(archetype.spawn(
+            &server,
+            &mut assets,
+            &scale,
+            &mut atlases,
+            &window,
+        ));
+        enemy.with_children(|parent| {
+            for weak_point in archetype.weak_points.iter() {
+                parent.spawn_bundle(weak_point.spawn(&server, &mut assets, &mut atlases));
+            }
+        });
+
+        // Leave an engine trail unless fixed to the ground.
+        if !matches!(archetype.movement, Movement::Scroll) {
+            let offset = Vec2::new(0.0, archetype.frame_size.1 * scale.scale / 2.0);
+            enemy.insert(Emitter::new(ParticleEffect::EnemyExhaust, offset, 20.0));
+        }
+
+        // Decrement enemies left.
+        enemies_left.count -= 1;
 
         // Reset spawn timer with a random duration.
         let delay = rng.gen_range(level.delay.clone());
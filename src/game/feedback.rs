@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::game::animation::Animation;
+use crate::game::particle::{ParticleBurst, ParticleEffect};
+use crate::game::player::{Downed, InvulnTimer, Player};
+use crate::game::GameState;
+
+/// Tint for the start of a hit flash, bright enough to wash the sprite out to white.
+const FLASH_WHITE: Color = Color::rgb(4.0, 4.0, 4.0);
+
+/// Tint for the end of a hit flash.
+const FLASH_RED: Color = Color::rgb(1.0, 0.35, 0.35);
+
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<EnemyHit>().add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(blink_invulnerable_players.system())
+                .with_system(flash_hit_enemies.system().label("flash_hit_enemies"))
+                .with_system(update_hit_flashes.system().after("flash_hit_enemies")),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct EnemyHit {
+    pub enemy: Entity,
+    pub position: Vec2,
+}
+
+#[derive(Debug)]
+pub struct HitFlash {
+    timer: Timer,
+}
+
+impl HitFlash {
+    /// Create a flash lasting a number of seconds, starting inactive.
+    pub fn new(seconds: f32) -> Self {
+        let mut timer = Timer::from_seconds(seconds, false);
+        timer.set_elapsed(timer.duration());
+
+        Self { timer }
+    }
+
+    /// Start flashing from the beginning.
+    pub fn flash(&mut self) {
+        self.timer.reset();
+    }
+}
+
+fn blink_invulnerable_players(
+    mut query: Query<(Option<&Downed>, &InvulnTimer, &mut Visible), With<Player>>,
+) {
+    for (downed, invuln_timer, mut visible) in query.iter_mut() {
+        // Blink for as long as the player can't be hit.
+        visible.is_visible = downed.is_some()
+            || invuln_timer.finished()
+            || (invuln_timer.elapsed_secs() * 16.0) as u32 % 2 == 0;
+    }
+}
+
+fn flash_hit_enemies(
+    mut events: EventReader<EnemyHit>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut query: Query<(&mut HitFlash, Option<&mut Animation>)>,
+) {
+    for event in events.iter() {
+        if let Ok((mut flash, animation)) = query.get_mut(event.enemy) {
+            flash.flash();
+
+            // Play a hit reaction if the enemy has one.
+            if let Some(mut animation) = animation {
+                if animation.has_clip("hit") {
+                    animation.play("hit");
+                }
+            }
+        }
+
+        // Spawn a small burst of sparks where the hit landed.
+        bursts.send(ParticleBurst {
+            count: 2,
+            effect: ParticleEffect::Impact,
+            position: event.position,
+        });
+    }
+}
+
+fn update_hit_flashes(time: Res<Time>, mut query: Query<(&mut HitFlash, &mut TextureAtlasSprite)>) {
+    for (mut flash, mut sprite) in query.iter_mut() {
+        // Flash white then red, and restore the color once finished.
+        flash.timer.tick(time.delta());
+        sprite.color = if flash.timer.just_finished() {
+            Color::WHITE
+        } else if flash.timer.finished() {
+            continue;
+        } else if flash.timer.percent() < 0.5 {
+            FLASH_WHITE
+        } else {
+            FLASH_RED
+        };
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::game::bullet::Bullet;
+use crate::game::collision::Hitbox;
+use crate::game::enemy::EnemyFaction;
+use crate::game::particle::{ParticleBurst, ParticleEffect};
+use crate::game::player::{Downed, Player, Power, Score};
+use crate::game::GameState;
+
+pub struct GrazePlugin;
+
+impl Plugin for GrazePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(graze_enemy_bullets.system().label("graze_enemy_bullets")),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct Graze {
+    pub count: u32,
+    pub points: u32,
+    pub radius: f32,
+}
+
+impl Graze {
+    /// Create a new graze counter.
+    pub fn new(radius: f32, points: u32) -> Self {
+        Self {
+            count: 0,
+            points,
+            radius,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GrazeMeter {
+    pub charge: u32,
+    pub capacity: u32,
+}
+
+impl GrazeMeter {
+    /// Create an empty meter.
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            charge: 0,
+            capacity,
+        }
+    }
+
+    /// Add charge, returning whether the meter filled up.
+    pub fn charge(&mut self, amount: u32) -> bool {
+        self.charge += amount;
+        if self.charge >= self.capacity {
+            self.charge -= self.capacity;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Grazed;
+
+fn graze_enemy_bullets(
+    mut commands: Commands,
+    mut bursts: EventWriter<ParticleBurst>,
+    bullets: Query<
+        (Entity, &Hitbox, &Transform),
+        (With<Bullet>, With<EnemyFaction>, Without<Grazed>),
+    >,
+    mut players: Query<
+        (
+            &mut Graze,
+            Option<&mut GrazeMeter>,
+            &Hitbox,
+            &mut Power,
+            &mut Score,
+            &Transform,
+        ),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for (mut graze, mut meter, player_hitbox, mut power, mut score, player_transform) in
+        players.iter_mut()
+    {
+        let player_position = player_transform.translation.truncate();
+        for (entity, hitbox, transform) in bullets.iter() {
+            // Check if within graze radius without hitting.
+            let position = transform.translation.truncate();
+            let distance = player_position.distance_squared(position);
+            let hit_radius = player_hitbox.radius + hitbox.radius;
+            let graze_radius = graze.radius + hitbox.radius;
+            if distance < hit_radius * hit_radius || distance >= graze_radius * graze_radius {
+                continue;
+            }
+
+            // Only count each bullet once.
+            commands.entity(entity).insert(Grazed);
+            graze.count += 1;
+            score.0 += graze.points;
+
+            // Charge power level.
+            if let Some(meter) = meter.as_mut() {
+                if meter.charge(1) {
+                    power.increase();
+                }
+            }
+
+            // Spawn sparks between the bullet and the player.
+            bursts.send(ParticleBurst {
+                count: 3,
+                effect: ParticleEffect::Graze,
+                position: position.lerp(player_position, 0.5),
+            });
+        }
+    }
+}
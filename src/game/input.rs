@@ -1,7 +1,15 @@
 use bevy::prelude::*;
 
-use crate::game::bullet::{Bullet, FireRate};
-use crate::game::player::{Player, PlayerFaction, Speed};
+use crate::game::armor::Defenses;
+use crate::game::assets::GameAssets;
+use crate::game::bullet::{Bullet, CancelBullets, Damage, FireRate, FiredBy};
+use crate::game::camera::CameraEffect;
+use crate::game::enemy::{Enemy, Health, LastHitBy};
+use crate::game::laser::{Beam, Laser, LaserOwner};
+use crate::game::mixer::SoundEffect;
+use crate::game::player::{
+    Bombs, ChargeMeter, Downed, InvulnTimer, Player, PlayerFaction, Ship, Speed, Steering,
+};
 use crate::game::{GameState, SpriteScale};
 
 pub struct InputPlugin;
@@ -13,44 +21,295 @@ impl Plugin for InputPlugin {
         )
         .add_system_set(
             SystemSet::on_update(GameState::Playing)
-                .with_system(fire_bullets.system())
+                .with_system(charge_shot.system())
+                .with_system(fire_bullets.system().label("fire_player_bullets"))
                 .with_system(move_player.system().label("move_player"))
-                .with_system(toggle_paused.system()),
+                .with_system(toggle_paused.system().label("toggle_paused"))
+                .with_system(use_bomb.system()),
         );
     }
 }
 
-fn fire_bullets(
+#[derive(Clone, Copy, Debug)]
+pub enum Action {
+    Bomb,
+    Charge,
+    Fire,
+    Focus,
+    SwitchShip,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Controls {
+    pub bomb: KeyCode,
+    pub charge: KeyCode,
+    pub down: KeyCode,
+    pub fire: KeyCode,
+    pub focus: KeyCode,
+    pub gamepad: Gamepad,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub switch_ship: KeyCode,
+    pub up: KeyCode,
+}
+
+impl Controls {
+    /// Get the default controls for a player.
+    pub fn for_player(id: usize) -> Self {
+        match id {
+            0 => Self {
+                bomb: KeyCode::X,
+                charge: KeyCode::C,
+                down: KeyCode::Down,
+                fire: KeyCode::Z,
+                focus: KeyCode::LShift,
+                gamepad: Gamepad(0),
+                left: KeyCode::Left,
+                right: KeyCode::Right,
+                switch_ship: KeyCode::V,
+                up: KeyCode::Up,
+            },
+            _ => Self {
+                bomb: KeyCode::O,
+                charge: KeyCode::P,
+                down: KeyCode::K,
+                fire: KeyCode::U,
+                focus: KeyCode::RShift,
+                gamepad: Gamepad(id),
+                left: KeyCode::J,
+                right: KeyCode::L,
+                switch_ship: KeyCode::Y,
+                up: KeyCode::I,
+            },
+        }
+    }
+
+    /// Check if an action was just pressed.
+    pub fn just_pressed(
+        &self,
+        action: Action,
+        keys: &Input<KeyCode>,
+        buttons: &Input<GamepadButton>,
+    ) -> bool {
+        keys.just_pressed(self.key(action)) || buttons.just_pressed(self.button(action))
+    }
+
+    /// Check if an action was just released.
+    pub fn just_released(
+        &self,
+        action: Action,
+        keys: &Input<KeyCode>,
+        buttons: &Input<GamepadButton>,
+    ) -> bool {
+        keys.just_released(self.key(action)) || buttons.just_released(self.button(action))
+    }
+
+    /// Get the direction of movement, with a length of at most one.
+    pub fn movement(&self, keys: &Input<KeyCode>, axes: &Axis<GamepadAxis>) -> Vec2 {
+        let mut direction = Vec2::ZERO;
+        if keys.pressed(self.left) {
+            direction.x -= 1.0;
+        }
+        if keys.pressed(self.right) {
+            direction.x += 1.0;
+        }
+        if keys.pressed(self.up) {
+            direction.y += 1.0;
+        }
+        if keys.pressed(self.down) {
+            direction.y -= 1.0;
+        }
+
+        // Add the left stick, ignoring small movements.
+        let stick = {
+            let x = GamepadAxis(self.gamepad, GamepadAxisType::LeftStickX);
+            let y = GamepadAxis(self.gamepad, GamepadAxisType::LeftStickY);
+            Vec2::new(axes.get(x).unwrap_or(0.0), axes.get(y).unwrap_or(0.0))
+        };
+        if stick.length() > 0.2 {
+            direction += stick;
+        }
+
+        direction.clamp_length_max(1.0)
+    }
+
+    /// Check if an action is being held.
+    pub fn pressed(
+        &self,
+        action: Action,
+        keys: &Input<KeyCode>,
+        buttons: &Input<GamepadButton>,
+    ) -> bool {
+        keys.pressed(self.key(action)) || buttons.pressed(self.button(action))
+    }
+
+    /// Get the gamepad button bound to an action.
+    fn button(&self, action: Action) -> GamepadButton {
+        let button_type = match action {
+            Action::Bomb => GamepadButtonType::East,
+            Action::Charge => GamepadButtonType::West,
+            Action::Fire => GamepadButtonType::South,
+            Action::Focus => GamepadButtonType::RightTrigger,
+            Action::SwitchShip => GamepadButtonType::North,
+        };
+        GamepadButton(self.gamepad, button_type)
+    }
+
+    /// Get the key bound to an action.
+    fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::Bomb => self.bomb,
+            Action::Charge => self.charge,
+            Action::Fire => self.fire,
+            Action::Focus => self.focus,
+            Action::SwitchShip => self.switch_ship,
+        }
+    }
+}
+
+fn charge_shot(
     mut commands: Commands,
-    server: Res<AssetServer>,
-    audio: Res<Audio>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    assets: Res<GameAssets>,
+    mut sounds: EventWriter<SoundEffect>,
     keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
     scale: Res<SpriteScale>,
     time: Res<Time>,
-    mut query: Query<(&mut FireRate, &Transform), With<Player>>,
+    mut query: Query<
+        (
+            Entity,
+            &mut ChargeMeter,
+            &Controls,
+            &mut TextureAtlasSprite,
+            &Transform,
+        ),
+        (With<Player>, Without<Downed>),
+    >,
 ) {
-    for (mut fire_rate, transform) in query.iter_mut() {
-        // Fire when holding Z.
-        fire_rate.tick(time.delta());
-        if keys.pressed(KeyCode::Z) && fire_rate.finished() {
+    for (entity, mut charge_meter, controls, mut sprite, transform) in query.iter_mut() {
+        // Charge while holding the charge button.
+        if controls.pressed(Action::Charge, &keys, &buttons) {
+            if charge_meter.charge(time.delta_seconds()) {
+                // Play audio.
+                sounds.send(SoundEffect::Charge);
+            }
+
+            // Tint the ship gold, pulsing once fully charged.
+            let percent = if charge_meter.is_full() {
+                0.75 + 0.25 * (time.seconds_since_startup() as f32 * 20.0).sin()
+            } else {
+                charge_meter.percent()
+            };
+            sprite.color = Color::rgb(1.0, 1.0 - 0.3 * percent, 1.0 - 0.8 * percent);
+        } else if controls.just_released(Action::Charge, &keys, &buttons) {
+            sprite.color = Color::WHITE;
+
+            // Ignore taps that barely charged.
+            let percent = charge_meter.release();
+            if percent < 0.2 {
+                continue;
+            }
+
             // Play audio.
-            let sound = server.load("sounds/fire.wav");
-            audio.play(sound);
+            sounds.send(SoundEffect::Fire);
 
-            for bullet in Bullet::Small.spawn(
-                &server,
-                &mut materials,
+            // Scale size and damage with charge.
+            let size = 1.0 + 2.0 * percent;
+            for mut bullet in Bullet::Charge.spawn(
+                &assets,
                 &scale,
                 transform.translation.truncate(),
                 Vec2::ZERO,
                 Vec2::ZERO,
                 90.0,
                 &[0.0],
-                12.0,
+                10.0,
                 1.0,
             ) {
-                commands.spawn_bundle(bullet).insert(PlayerFaction);
+                bullet.damage = Damage(1 + (9.0 * percent) as u32);
+                bullet.hitbox.radius *= size;
+                bullet.sprite.transform.scale *= size;
+                commands
+                    .spawn_bundle(bullet)
+                    .insert(PlayerFaction)
+                    .insert(FiredBy(entity));
+            }
+        }
+    }
+}
+
+fn fire_bullets(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    mut sounds: EventWriter<SoundEffect>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    scale: Res<SpriteScale>,
+    time: Res<Time>,
+    mut lasers: Query<(&mut Beam, &LaserOwner)>,
+    mut query: Query<
+        (
+            Entity,
+            Option<&ChargeMeter>,
+            &Controls,
+            &mut FireRate,
+            &Ship,
+            &Transform,
+        ),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for (entity, charge_meter, controls, mut fire_rate, ship, transform) in query.iter_mut() {
+        fire_rate.tick(time.delta());
+        let firing = controls.pressed(Action::Fire, &keys, &buttons);
+        match ship {
+            Ship::Fighter | Ship::Striker => {
+                // Fire when holding the fire button unless charging.
+                let charging = charge_meter.map_or(false, |m| m.is_charging());
+                if firing && !charging && fire_rate.finished() {
+                    // Play audio.
+                    sounds.send(SoundEffect::Fire);
+
+                    for bullet in Bullet::Small.spawn(
+                        &assets,
+                        &scale,
+                        transform.translation.truncate(),
+                        Vec2::ZERO,
+                        Vec2::ZERO,
+                        90.0,
+                        &[0.0],
+                        12.0,
+                        1.0,
+                    ) {
+                        commands
+                            .spawn_bundle(bullet)
+                            .insert(PlayerFaction)
+                            .insert(FiredBy(entity));
+                    }
+                }
+            }
+            Ship::Lancer => {
+                // Sustain a beam while holding the fire button.
+                if firing {
+                    match lasers.iter_mut().find(|(_, owner)| owner.0 == entity) {
+                        Some((mut beam, _)) => beam.sustain(),
+                        None => {
+                            // Play audio.
+                            sounds.send(SoundEffect::Fire);
+
+                            commands
+                                .spawn_bundle(Laser::Focused.spawn(
+                                    &assets,
+                                    entity,
+                                    transform.translation.truncate(),
+                                    90.0,
+                                    0.0,
+                                    1.0,
+                                ))
+                                .insert(PlayerFaction);
+                        }
+                    }
+                }
             }
         }
     }
@@ -58,33 +317,23 @@ fn fire_bullets(
 
 fn move_player(
     keys: Res<Input<KeyCode>>,
-    mut query: Query<(&Speed, &mut Transform), With<Player>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut query: Query<
+        (&Controls, &Speed, &mut Steering, &mut Transform),
+        (With<Player>, Without<Downed>),
+    >,
 ) {
-    for (speed, mut transform) in query.iter_mut() {
-        // Move at half speed when holding down shift.
-        let speed = if keys.pressed(KeyCode::LShift) {
+    for (controls, speed, mut steering, mut transform) in query.iter_mut() {
+        // Move at half speed when focused.
+        let speed = if controls.pressed(Action::Focus, &keys, &buttons) {
             speed.0 / 2.0
         } else {
             speed.0
         };
 
-        // Calculate velocity.
-        let mut velocity = Vec2::ZERO;
-        if keys.pressed(KeyCode::Left) {
-            velocity -= Vec2::new(speed, 0.0);
-        }
-        if keys.pressed(KeyCode::Right) {
-            velocity += Vec2::new(speed, 0.0);
-        }
-        if keys.pressed(KeyCode::Up) {
-            velocity += Vec2::new(0.0, speed);
-        }
-        if keys.pressed(KeyCode::Down) {
-            velocity -= Vec2::new(0.0, speed);
-        }
-
-        // Clamp velocity to current speed.
-        transform.translation += velocity.clamp_length_max(speed).extend(0.0);
+        steering.0 = controls.movement(&keys, &axes) * speed;
+        transform.translation += steering.0.extend(0.0);
     }
 }
 
@@ -101,3 +350,38 @@ fn toggle_paused(mut state: ResMut<State<GameState>>, mut keys: ResMut<Input<Key
         keys.reset(KeyCode::Escape);
     }
 }
+
+fn use_bomb(
+    mut sounds: EventWriter<SoundEffect>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut cancel_events: EventWriter<CancelBullets>,
+    mut effects: EventWriter<CameraEffect>,
+    mut enemies: Query<(&Defenses, &mut Health, &mut LastHitBy), With<Enemy>>,
+    mut player: Query<
+        (Entity, &mut Bombs, &Controls, &mut InvulnTimer),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for (entity, mut bombs, controls, mut invuln_timer) in player.iter_mut() {
+        // Bomb when pressing the bomb button.
+        if controls.just_pressed(Action::Bomb, &keys, &buttons) && bombs.0 > 0 {
+            bombs.0 -= 1;
+
+            // Play audio.
+            sounds.send(SoundEffect::Explosion);
+
+            // Clear the screen of bullets and damage every enemy.
+            cancel_events.send(CancelBullets::all());
+            effects.send(CameraEffect::Flash(0.8));
+            effects.send(CameraEffect::Shake(0.4));
+            for (defenses, mut health, mut last_hit_by) in enemies.iter_mut() {
+                health.damage(defenses.reduce(2));
+                last_hit_by.0 = Some(entity);
+            }
+
+            // Grant brief invulnerability.
+            invuln_timer.reset();
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::Deserialize;
+
+use crate::game::assets::GameAssets;
+use crate::game::bullet::{Bullet, CancelBullets, FiredBy};
+use crate::game::collision::{DespawnOutside, Hitbox};
+use crate::game::enemy::EnemyFaction;
+use crate::game::physics::{Acceleration, Velocity};
+use crate::game::player::{Downed, Player, Power, Score};
+use crate::game::{GameState, SpriteScale};
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(attract_items.system())
+                .with_system(cancel_bullets.system())
+                .with_system(collect_items.system()),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum Item {
+    Point,
+    Power,
+}
+
+impl Item {
+    /// Spawn a single item.
+    pub fn spawn_single(
+        self,
+        assets: &GameAssets,
+        scale: &SpriteScale,
+        position: Vec2,
+    ) -> ItemBundle {
+        let radius = match self {
+            Self::Point => 8.0,
+            Self::Power => 8.0,
+        };
+
+        // Pop upwards before falling.
+        let velocity = Velocity({
+            let mut rng = rand::thread_rng();
+            Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(1.0..3.0))
+        });
+
+        ItemBundle {
+            acceleration: Acceleration(Vec2::new(0.0, -0.1)),
+            despawn_outside: DespawnOutside,
+            hitbox: Hitbox {
+                radius: radius * scale.scale,
+            },
+            item: self,
+            sprite: SpriteBundle {
+                material: assets.item(self),
+                transform: scale.translate(position.extend(4.0)),
+                ..Default::default()
+            },
+            velocity,
+        }
+    }
+
+    /// Get the score awarded for collecting this item.
+    pub fn points(self) -> u32 {
+        match self {
+            Self::Point => 100,
+            Self::Power => 10,
+        }
+    }
+
+    /// Get the path of the item's texture.
+    pub fn texture(self) -> &'static str {
+        match self {
+            Self::Point => "textures/items/point.png",
+            Self::Power => "textures/items/power.png",
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct ItemBundle {
+    pub acceleration: Acceleration,
+    pub despawn_outside: DespawnOutside,
+    pub hitbox: Hitbox,
+    pub item: Item,
+    #[bundle]
+    pub sprite: SpriteBundle,
+    pub velocity: Velocity,
+}
+
+fn attract_items(
+    mut items: Query<(&Transform, &mut Velocity), With<Item>>,
+    players: Query<&Transform, (With<Player>, Without<Downed>)>,
+) {
+    for (transform, mut velocity) in items.iter_mut() {
+        // Find the closest player.
+        let position = transform.translation.truncate();
+        let closest = players
+            .iter()
+            .map(|t| t.translation.truncate() - position)
+            .min_by(|a, b| a.length_squared().partial_cmp(&b.length_squared()).unwrap());
+
+        // Home in on the player once close enough.
+        match closest {
+            Some(offset) if offset.length() > 0.0 && offset.length() < 120.0 => {
+                velocity.0 = offset.normalize() * 8.0;
+            }
+            _ => velocity.0.y = velocity.0.y.max(-3.0),
+        }
+    }
+}
+
+fn cancel_bullets(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    scale: Res<SpriteScale>,
+    mut events: EventReader<CancelBullets>,
+    bullets: Query<(Entity, Option<&FiredBy>, &Transform), (With<Bullet>, With<EnemyFaction>)>,
+) {
+    // Gather bullets across every event first, so overlapping cancels only convert each once.
+    let mut cancelled = HashSet::new();
+    for event in events.iter() {
+        for (entity, fired_by, _) in bullets.iter() {
+            // Skip bullets fired by someone else.
+            if let Some(owner) = event.owner {
+                if fired_by.map(|f| f.0) != Some(owner) {
+                    continue;
+                }
+            }
+
+            cancelled.insert(entity);
+        }
+    }
+
+    for entity in cancelled {
+        if let Ok((_, _, transform)) = bullets.get(entity) {
+            commands.entity(entity).despawn();
+            commands.spawn_bundle(Item::Point.spawn_single(
+                &assets,
+                &scale,
+                transform.translation.truncate(),
+            ));
+        }
+    }
+}
+
+fn collect_items(
+    mut commands: Commands,
+    items: Query<(Entity, &Hitbox, &Item, &Transform)>,
+    mut players: Query<
+        (&Hitbox, &mut Power, &mut Score, &Transform),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for (entity, hitbox, item, transform) in items.iter() {
+        for (player_hitbox, mut power, mut score, player_transform) in players.iter_mut() {
+            // Check for collision.
+            let distance = player_transform
+                .translation
+                .truncate()
+                .distance_squared(transform.translation.truncate());
+            let radius_sum = player_hitbox.radius + hitbox.radius;
+            if distance < radius_sum * radius_sum {
+                commands.entity(entity).despawn();
+                score.0 += item.points();
+                if let Item::Power = item {
+                    power.increase();
+                }
+
+                // Only the first player to touch an item collects it.
+                break;
+            }
+        }
+    }
+}
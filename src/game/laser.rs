@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+
+use crate::game::assets::GameAssets;
+use crate::game::collision;
+use crate::game::GameState;
+
+pub struct LaserPlugin;
+
+impl Plugin for LaserPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(update_lasers.system().label("update_lasers")),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct Beam {
+    pub angle: f32,
+    pub damage: u32,
+    pub growth: f32,
+    pub length: f32,
+    pub max_length: f32,
+    pub origin: Vec2,
+    pub sweep: f32,
+    pub width: f32,
+    duration: Timer,
+    material: Handle<ColorMaterial>,
+    telegraph: Timer,
+    tick: Timer,
+}
+
+impl Beam {
+    /// Get the start and end points of the beam.
+    pub fn endpoints(&self) -> (Vec2, Vec2) {
+        let angle = self.angle.to_radians();
+        let direction = Vec2::new(angle.cos(), angle.sin());
+        (self.origin, self.origin + direction * self.length)
+    }
+
+    /// Check if a circle overlaps the beam.
+    pub fn hits(&self, position: Vec2, radius: f32) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+
+        let (start, end) = self.endpoints();
+        let radius_sum = self.width / 2.0 + radius;
+        collision::segment_distance_squared(position, start, end) < radius_sum * radius_sum
+    }
+
+    /// Check if the telegraph has finished.
+    pub fn is_active(&self) -> bool {
+        self.telegraph.finished()
+    }
+
+    /// Check if the beam should deal damage this frame.
+    pub fn ready(&self) -> bool {
+        self.is_active() && self.tick.just_finished()
+    }
+
+    /// Keep the beam alive for another full duration.
+    pub fn sustain(&mut self) {
+        self.duration.reset();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Laser {
+    Focused,
+    Sweeping,
+}
+
+impl Laser {
+    /// Get the color of the laser's beam.
+    pub fn color(self) -> Color {
+        match self {
+            Self::Focused => Color::rgb(0.6, 0.9, 1.0),
+            Self::Sweeping => Color::rgb(0.9, 0.5, 1.0),
+        }
+    }
+
+    /// Spawn a laser attached to an owner.
+    pub fn spawn(
+        self,
+        assets: &GameAssets,
+        owner: Entity,
+        origin: Vec2,
+        angle: f32,
+        sweep: f32,
+        z_index: f32,
+    ) -> LaserBundle {
+        let (damage, duration, growth, max_length, telegraph, width) = match self {
+            Self::Focused => (1, 0.1, 40.0, 1200.0, 0.0, 12.0),
+            Self::Sweeping => (1, 1.2, 24.0, 1200.0, 0.6, 18.0),
+        };
+
+        // Get material handles.
+        let (material, beam_material) = assets.laser(self);
+
+        LaserBundle {
+            beam: Beam {
+                angle,
+                damage,
+                growth,
+                length: 0.0,
+                max_length,
+                origin,
+                sweep,
+                width,
+                duration: Timer::from_seconds(duration, false),
+                material: beam_material,
+                telegraph: Timer::from_seconds(telegraph, false),
+                tick: Timer::from_seconds(0.1, true),
+            },
+            laser: self,
+            owner: LaserOwner(owner),
+            sprite: SpriteBundle {
+                material,
+                sprite: Sprite::new(Vec2::ZERO),
+                transform: Transform::from_translation(origin.extend(z_index)),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct LaserBundle {
+    pub beam: Beam,
+    pub laser: Laser,
+    pub owner: LaserOwner,
+    #[bundle]
+    pub sprite: SpriteBundle,
+}
+
+#[derive(Debug)]
+pub struct LaserOwner(pub Entity);
+
+fn update_lasers(
+    mut commands: Commands,
+    time: Res<Time>,
+    owners: Query<&Transform, Without<Beam>>,
+    mut lasers: Query<(
+        Entity,
+        &mut Beam,
+        &mut Handle<ColorMaterial>,
+        &LaserOwner,
+        &mut Sprite,
+        &mut Transform,
+    )>,
+) {
+    for (entity, mut beam, mut material, owner, mut sprite, mut transform) in lasers.iter_mut() {
+        // Despawn once the owner is gone.
+        beam.origin = match owners.get(owner.0) {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+
+        // Show a thin warning line until the telegraph finishes.
+        beam.telegraph.tick(time.delta());
+        let (width, length) = if beam.is_active() {
+            // Despawn once the beam has expired.
+            beam.duration.tick(time.delta());
+            if beam.duration.finished() {
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            if *material != beam.material {
+                *material = beam.material.clone();
+            }
+
+            // Grow and sweep the beam.
+            beam.tick.tick(time.delta());
+            beam.length = (beam.length + beam.growth).min(beam.max_length);
+            beam.angle += beam.sweep;
+            (beam.width, beam.length)
+        } else {
+            (2.0, beam.max_length)
+        };
+
+        // Stretch the sprite between the endpoints.
+        let angle = beam.angle.to_radians();
+        let center = beam.origin + Vec2::new(angle.cos(), angle.sin()) * length / 2.0;
+        sprite.size = Vec2::new(width, length);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        transform.rotation = Quat::from_rotation_z(angle - std::f32::consts::FRAC_PI_2);
+    }
+}
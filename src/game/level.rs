@@ -3,7 +3,9 @@ use std::ops::Range;
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
-use crate::game::enemy::Enemy;
+use crate::game::background::{BackgroundTheme, TileLayer};
+use crate::game::enemy::EnemyKind;
+use crate::game::starfield::{ScrollSpeed, StarfieldConfig};
 use crate::game::GameState;
 
 pub struct LevelPlugin;
@@ -13,7 +15,8 @@ impl Plugin for LevelPlugin {
         app.add_system_set(
             SystemSet::on_exit(GameState::GameOver).with_system(reset_level.system()),
         )
-        .add_startup_system(setup.system());
+        .add_startup_system(setup.system())
+        .add_system(change_scroll_speed.system());
     }
 }
 
@@ -67,8 +70,29 @@ impl SpawnTimer {
 #[derive(Debug)]
 pub struct Level {
     pub delay: Range<u64>,
-    pub enemies: Vec<(Enemy, u32)>,
+    pub enemies: Vec<(EnemyKind, u32)>,
     pub enemy_limit: u32,
+    pub music: String,
+    pub scroll_speed: f32,
+    pub theme: BackgroundTheme,
+}
+
+/// Turn rows of map text into owned strings.
+fn tile_map(rows: &[&str]) -> Vec<String> {
+    rows.iter().map(|row| row.to_string()).collect()
+}
+
+fn change_scroll_speed(
+    levels: Res<Vec<Level>>,
+    mut scroll_speed: ResMut<ScrollSpeed>,
+    query: Query<&CurrentLevel, Changed<CurrentLevel>>,
+) {
+    // Ease towards the new level's pace.
+    for current in query.iter() {
+        if let Some(index) = current.level {
+            scroll_speed.target = levels[index].scroll_speed;
+        }
+    }
 }
 
 fn reset_level(
@@ -88,18 +112,99 @@ fn reset_level(
     timer.reset(1000);
 }
 
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, server: Res<AssetServer>) {
     // Initialize levels.
     let levels = vec![
         Level {
             delay: 800..3200,
-            enemies: vec![(Enemy::Basic, 1)],
+            enemies: vec![(EnemyKind::named(&server, "basic"), 1)],
             enemy_limit: 10,
+            music: "music/level1.wav".to_string(),
+            scroll_speed: 2.0,
+            theme: BackgroundTheme {
+                gradient: (Color::rgb(0.02, 0.02, 0.08), Color::BLACK),
+                starfield: StarfieldConfig::default(),
+                tiles: None,
+            },
         },
         Level {
             delay: 800..3200,
-            enemies: vec![(Enemy::Basic, 4), (Enemy::Bomber, 1)],
+            enemies: vec![
+                (EnemyKind::named(&server, "basic"), 4),
+                (EnemyKind::named(&server, "bomber"), 1),
+                (EnemyKind::named(&server, "drifter"), 2),
+                (EnemyKind::named(&server, "kamikaze"), 1),
+                (EnemyKind::named(&server, "lancer"), 1),
+                (EnemyKind::named(&server, "seeder"), 1),
+                (EnemyKind::named(&server, "warden"), 1),
+            ],
             enemy_limit: 40,
+            music: "music/level2.wav".to_string(),
+            scroll_speed: 2.5,
+            theme: BackgroundTheme {
+                gradient: (Color::rgb(0.1, 0.04, 0.12), Color::rgb(0.02, 0.0, 0.03)),
+                starfield: StarfieldConfig::default()
+                    .with_density(0.8)
+                    .with_nebula_tint(Color::rgba(1.0, 0.6, 0.4, 0.5)),
+                tiles: Some(TileLayer {
+                    grid: (4, 1),
+                    map: tile_map(&[
+                        "01..............20",
+                        "2..............310",
+                        "13...............2",
+                        "0...............13",
+                        "23..............0.",
+                        ".0.............321",
+                        "1...............0.",
+                        "3...............21",
+                    ]),
+                    speed: 1.25,
+                    texture: "textures/backgrounds/asteroids.png".to_string(),
+                    tile_size: (32.0, 32.0),
+                    tint: Color::rgb(0.8, 0.8, 0.8),
+                    z_index: 0.5,
+                }),
+            },
+        },
+        Level {
+            delay: 600..2400,
+            enemies: vec![
+                (EnemyKind::named(&server, "basic"), 2),
+                (EnemyKind::named(&server, "bulwark"), 1),
+                (EnemyKind::named(&server, "carrier"), 1),
+                (EnemyKind::named(&server, "glider"), 1),
+                (EnemyKind::named(&server, "kamikaze"), 1),
+                (EnemyKind::named(&server, "looper"), 1),
+                (EnemyKind::named(&server, "sentry"), 1),
+                (EnemyKind::named(&server, "swooper"), 2),
+                (EnemyKind::named(&server, "turret"), 2),
+                (EnemyKind::named(&server, "weaver"), 2),
+            ],
+            enemy_limit: 50,
+            music: "music/level3.wav".to_string(),
+            scroll_speed: 3.5,
+            theme: BackgroundTheme {
+                gradient: (Color::rgb(0.02, 0.07, 0.1), Color::rgb(0.0, 0.01, 0.02)),
+                starfield: StarfieldConfig::default()
+                    .with_density(0.6)
+                    .with_nebula_tint(Color::rgba(0.5, 0.8, 1.0, 0.4)),
+                tiles: Some(TileLayer {
+                    grid: (4, 1),
+                    map: tile_map(&[
+                        "01..............10",
+                        "02..............20",
+                        "31..............13",
+                        "00..............00",
+                        "10..............01",
+                        "20..............02",
+                    ]),
+                    speed: 1.0,
+                    texture: "textures/backgrounds/hull.png".to_string(),
+                    tile_size: (32.0, 32.0),
+                    tint: Color::rgb(0.7, 0.7, 0.7),
+                    z_index: 0.5,
+                }),
+            },
         },
     ];
 
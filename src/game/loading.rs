@@ -0,0 +1,227 @@
+use bevy::asset::{HandleId, LoadState};
+use bevy::prelude::*;
+
+use crate::game::archetype::EnemyArchetype;
+use crate::game::assets::GameAssets;
+use crate::game::enemy::EnemyKind;
+use crate::game::level::Level;
+use crate::game::music::MusicTracks;
+use crate::game::settings::SettingsError;
+use crate::game::GameState;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(SystemSet::on_enter(GameState::Loading).with_system(setup.system()))
+            .add_system_set(
+                SystemSet::on_update(GameState::Loading).with_system(check_progress.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Loading).with_system(despawn_loading_screen.system()),
+            );
+    }
+}
+
+/// Handles to everything loaded up front, kept alive so the assets stay loaded.
+#[derive(Debug, Default)]
+pub struct LoadingAssets {
+    /// Enemy archetypes whose own textures haven't been queued yet.
+    archetypes: Vec<Handle<EnemyArchetype>>,
+    errors: Vec<String>,
+    handles: Vec<HandleUntyped>,
+}
+
+impl LoadingAssets {
+    /// Start loading a file by path.
+    fn load(&mut self, server: &AssetServer, path: &str) {
+        self.queue(server.load_untyped(path));
+    }
+
+    /// Wait on a handle, unless it is generated in memory or already queued.
+    fn queue(&mut self, handle: HandleUntyped) {
+        if let HandleId::AssetPathId(_) = handle.id {
+            if !self.handles.iter().any(|queued| queued.id == handle.id) {
+                self.handles.push(handle);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadingScreen;
+
+#[derive(Debug)]
+pub struct LoadingText;
+
+#[derive(Debug)]
+pub struct ProgressBar;
+
+fn check_progress(
+    server: Res<AssetServer>,
+    archetypes: Res<Assets<EnemyArchetype>>,
+    mut loading: ResMut<LoadingAssets>,
+    mut state: ResMut<State<GameState>>,
+    mut bars: Query<&mut Style, With<ProgressBar>>,
+    mut texts: Query<&mut Text, With<LoadingText>>,
+) {
+    // Queue each archetype's textures once it has loaded, since they aren't known before then.
+    let mut textures = Vec::new();
+    loading
+        .archetypes
+        .retain(|handle| match archetypes.get(handle) {
+            Some(archetype) => {
+                textures.extend(archetype.textures());
+                false
+            }
+            None => true,
+        });
+    for texture in textures {
+        loading.load(&server, texture);
+    }
+
+    let mut errors = loading.errors.clone();
+    let mut loaded = 0;
+    for handle in loading.handles.iter() {
+        match server.get_load_state(handle.id) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed => {
+                // Name the file, since the loader's own error only goes to the log.
+                let path = server
+                    .get_handle_path(handle.id)
+                    .map(|path| path.path().display().to_string())
+                    .unwrap_or_else(|| "unknown asset".to_string());
+                errors.push(format!("{} is missing or corrupt", path));
+            }
+            _ => {}
+        }
+    }
+
+    // Fill the progress bar.
+    let total = loading.handles.len().max(1);
+    for mut style in bars.iter_mut() {
+        style.size.width = Val::Percent(100.0 * loaded as f32 / total as f32);
+    }
+
+    // Stop on the error screen if anything failed.
+    if !errors.is_empty() {
+        for mut text in texts.iter_mut() {
+            text.sections[0].value = format!("Failed to load\n\n{}", errors.join("\n"));
+            text.sections[0].style.color = Color::rgb(1.0, 0.4, 0.4);
+        }
+        return;
+    }
+
+    if loaded == loading.handles.len() && loading.archetypes.is_empty() {
+        state.set(GameState::Playing).unwrap();
+    }
+}
+
+fn despawn_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    assets: Res<GameAssets>,
+    levels: Res<Vec<Level>>,
+    settings_error: Res<SettingsError>,
+    tracks: Res<MusicTracks>,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    // Wait on every file the game refers to, so anything missing is caught here.
+    let mut loading = LoadingAssets::default();
+    for handle in assets.handles(&materials, &atlases) {
+        loading.queue(handle);
+    }
+    for level in levels.iter() {
+        for (kind, _) in level.enemies.iter() {
+            match kind {
+                EnemyKind::Archetype(handle) => {
+                    loading.queue(handle.clone_untyped());
+                    loading.archetypes.push(handle.clone());
+                }
+                EnemyKind::BuiltIn(enemy) => {
+                    for texture in enemy.archetype().textures() {
+                        loading.load(&server, texture);
+                    }
+                }
+            }
+        }
+        for texture in level.theme.textures() {
+            loading.load(&server, texture);
+        }
+        loading.load(&server, &level.music);
+    }
+    for track in [&tracks.boss, &tracks.game_over].iter() {
+        loading.load(&server, track);
+    }
+
+    // Settings are read before any assets, so report a bad file alongside them.
+    if let Some(error) = &settings_error.0 {
+        loading.errors.push(error.clone());
+    }
+    commands.insert_resource(loading);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(Color::BLACK.into()),
+            ..Default::default()
+        })
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            bottom: Val::Px(16.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "Loading",
+                        TextStyle {
+                            font: assets.font.clone(),
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(LoadingText);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(400.0), Val::Px(16.0)),
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::rgb(0.2, 0.2, 0.25).into()),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..Default::default()
+                            },
+                            material: materials.add(Color::WHITE.into()),
+                            ..Default::default()
+                        })
+                        .insert(ProgressBar);
+                });
+        });
+}
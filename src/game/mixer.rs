@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioChannel};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::assets::GameAssets;
+
+/// Number of channels sound effects can be pitched in.
+const SFX_CHANNELS: usize = 8;
+
+/// Volume change per key press.
+const VOLUME_STEP: f32 = 0.1;
+
+pub struct MixerPlugin;
+
+impl Plugin for MixerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<SoundEffect>()
+            .insert_resource(Mixer::default())
+            .add_system(adjust_volume.system())
+            .add_system(play_sounds.system());
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 0.6,
+            sfx: 0.8,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// Get the final volume for music.
+    pub fn music_volume(&self) -> f32 {
+        (self.master * self.music).max(0.0).min(1.0)
+    }
+
+    /// Get the final volume for sound effects.
+    pub fn sfx_volume(&self) -> f32 {
+        (self.master * self.sfx).max(0.0).min(1.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct Mixer {
+    /// Time each channel's current sound finishes.
+    busy_until: Vec<f64>,
+    channels: Vec<AudioChannel>,
+    last_played: HashMap<SoundEffect, f64>,
+    voices: HashMap<SoundEffect, Vec<f64>>,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self {
+            busy_until: vec![0.0; SFX_CHANNELS],
+            channels: (0..SFX_CHANNELS)
+                .map(|index| AudioChannel::new(format!("sfx_{}", index)))
+                .collect(),
+            last_played: HashMap::new(),
+            voices: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SoundEffect {
+    Charge,
+    Explosion,
+    Fire,
+    PlayerHit,
+}
+
+impl SoundEffect {
+    /// Get the settings used to play this sound.
+    pub fn spec(self) -> SoundSpec {
+        match self {
+            Self::Charge => SoundSpec {
+                cooldown: 0.2,
+                length: 0.35,
+                max_voices: 1,
+                path: "sounds/charge.wav",
+                pitch: 0.0,
+            },
+            Self::Explosion => SoundSpec {
+                cooldown: 0.06,
+                length: 0.6,
+                max_voices: 4,
+                path: "sounds/explosion.wav",
+                pitch: 0.1,
+            },
+            Self::Fire => SoundSpec {
+                cooldown: 0.05,
+                length: 0.4,
+                max_voices: 3,
+                path: "sounds/fire.wav",
+                pitch: 0.05,
+            },
+            Self::PlayerHit => SoundSpec {
+                cooldown: 0.1,
+                length: 0.65,
+                max_voices: 2,
+                path: "sounds/player_hit.wav",
+                pitch: 0.05,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SoundSpec {
+    pub cooldown: f64,
+    pub length: f64,
+    pub max_voices: usize,
+    pub path: &'static str,
+    pub pitch: f32,
+}
+
+fn adjust_volume(mut settings: ResMut<AudioSettings>, mut keys: ResMut<Input<KeyCode>>) {
+    // Lower and raise the master, music and effect volumes with pairs of function keys.
+    let bindings = [
+        (KeyCode::F1, KeyCode::F2),
+        (KeyCode::F3, KeyCode::F4),
+        (KeyCode::F5, KeyCode::F6),
+    ];
+    for (i, (down, up)) in bindings.iter().copied().enumerate() {
+        let step = if keys.just_pressed(down) {
+            -VOLUME_STEP
+        } else if keys.just_pressed(up) {
+            VOLUME_STEP
+        } else {
+            continue;
+        };
+
+        // Round to the nearest step, so repeated presses land on tidy values.
+        let volume = match i {
+            0 => &mut settings.master,
+            1 => &mut settings.music,
+            _ => &mut settings.sfx,
+        };
+        *volume = (((*volume + step) / VOLUME_STEP).round() * VOLUME_STEP)
+            .max(0.0)
+            .min(1.0);
+        keys.reset(down);
+        keys.reset(up);
+    }
+}
+
+fn play_sounds(
+    assets: Res<GameAssets>,
+    audio: Res<Audio>,
+    settings: Res<AudioSettings>,
+    time: Res<Time>,
+    mut mixer: ResMut<Mixer>,
+    mut events: EventReader<SoundEffect>,
+) {
+    let mut rng = rand::thread_rng();
+    let now = time.seconds_since_startup();
+
+    for sound in events.iter() {
+        let spec = sound.spec();
+
+        // Skip sounds retriggered too quickly.
+        if let Some(last) = mixer.last_played.get(sound) {
+            if now - last < spec.cooldown {
+                continue;
+            }
+        }
+
+        // Skip sounds with too many copies still playing.
+        let voices = mixer.voices.entry(*sound).or_insert_with(Vec::new);
+        voices.retain(|end| *end > now);
+        if voices.len() >= spec.max_voices {
+            continue;
+        }
+        voices.push(now + spec.length);
+        mixer.last_played.insert(*sound, now);
+
+        // Pitch each sound on an idle channel, so sounds still playing aren't bent along with it.
+        match mixer.busy_until.iter().position(|end| *end <= now) {
+            Some(index) => {
+                mixer.busy_until[index] = now + spec.length;
+                let channel = &mixer.channels[index];
+                let rate = 1.0 + rng.gen_range(-spec.pitch..=spec.pitch);
+                audio.set_volume_in_channel(settings.sfx_volume(), channel);
+                audio.set_playback_rate_in_channel(rate, channel);
+                audio.play_in_channel(assets.sound(*sound), channel);
+            }
+            None => {
+                // Fall back to the main channel at normal pitch when every channel is busy.
+                audio.set_volume(settings.sfx_volume());
+                audio.play(assets.sound(*sound));
+            }
+        }
+    }
+}
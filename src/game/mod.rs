@@ -1,24 +1,60 @@
 use bevy::prelude::*;
 
-use animation::{AnimationPlugin, AnimationTimer};
+use animation::{Animation, AnimationPlugin};
+use archetype::ArchetypePlugin;
+use armor::ArmorPlugin;
+use assets::AssetsPlugin;
+use background::BackgroundPlugin;
+use behavior::BehaviorPlugin;
 use bullet::{Bullet, BulletPlugin};
+use camera::{CameraPlugin, CameraShake};
 use collision::CollisionPlugin;
+use display::{DisplayPlugin, PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH};
+use drone::{Drone, DronePlugin};
 use enemy::{Enemy, EnemyPlugin};
+use feedback::FeedbackPlugin;
+use graze::GrazePlugin;
 use input::InputPlugin;
+use item::{Item, ItemPlugin};
+use laser::{Beam, LaserPlugin};
 use level::LevelPlugin;
+use loading::LoadingPlugin;
+use mixer::MixerPlugin;
+use music::MusicPlugin;
+use particle::{Particle, ParticlePlugin};
+use path::PathPlugin;
 use physics::PhysicsPlugin;
 use player::PlayerPlugin;
+use settings::SettingsPlugin;
 use starfield::{Star, StarfieldPlugin};
 use ui::UiPlugin;
 
 mod animation;
+mod archetype;
+mod armor;
+mod assets;
+mod background;
+mod behavior;
 mod bullet;
+mod camera;
 mod collision;
+mod display;
+mod drone;
 mod enemy;
+mod feedback;
+mod graze;
 mod input;
+mod item;
+mod laser;
 mod level;
+mod loading;
+mod mixer;
+mod music;
+mod particle;
+mod path;
 mod physics;
 mod player;
+mod settings;
 mod starfield;
 mod ui;
 
@@ -27,16 +63,35 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_plugin(AnimationPlugin)
+            .add_plugin(ArchetypePlugin)
+            .add_plugin(ArmorPlugin)
+            .add_plugin(AssetsPlugin)
+            .add_plugin(BackgroundPlugin)
+            .add_plugin(BehaviorPlugin)
             .add_plugin(BulletPlugin)
+            .add_plugin(CameraPlugin)
             .add_plugin(CollisionPlugin)
+            .add_plugin(DisplayPlugin)
+            .add_plugin(DronePlugin)
             .add_plugin(EnemyPlugin)
+            .add_plugin(FeedbackPlugin)
+            .add_plugin(GrazePlugin)
             .add_plugin(InputPlugin)
+            .add_plugin(ItemPlugin)
+            .add_plugin(LaserPlugin)
             .add_plugin(LevelPlugin)
+            .add_plugin(LoadingPlugin)
+            .add_plugin(MixerPlugin)
+            .add_plugin(MusicPlugin)
+            .add_plugin(ParticlePlugin)
+            .add_plugin(PathPlugin)
             .add_plugin(PhysicsPlugin)
             .add_plugin(PlayerPlugin)
+            .add_plugin(SettingsPlugin)
             .add_plugin(StarfieldPlugin)
             .add_plugin(UiPlugin)
-            .add_state(GameState::Playing)
+            .insert_resource(WindowSize::playfield())
+            .add_state(GameState::Loading)
             .add_system_set(
                 SystemSet::on_exit(GameState::GameOver).with_system(despawn_everything.system()),
             )
@@ -47,6 +102,8 @@ impl Plugin for GamePlugin {
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum GameState {
     GameOver,
+    HitStop,
+    Loading,
     Paused,
     Playing,
 }
@@ -65,14 +122,14 @@ impl SpriteScale {
     /// Get a transform translated to a coordinate.
     pub fn translate(&self, translation: Vec3) -> Transform {
         let mut transform = Transform::from_translation(translation);
-        transform.scale = Vec3::new(self.scale, self.scale, 0.0);
+        transform.scale = Vec3::new(self.scale, self.scale, 1.0);
         transform
     }
 
     /// Get a transform translated to a coordinate.
     pub fn xyz(&self, x: f32, y: f32, z: f32) -> Transform {
         let mut transform = Transform::from_xyz(x, y, z);
-        transform.scale = Vec3::new(self.scale, self.scale, 0.0);
+        transform.scale = Vec3::new(self.scale, self.scale, 1.0);
         transform
     }
 }
@@ -84,35 +141,43 @@ pub struct WindowSize {
 }
 
 impl WindowSize {
-    /// Get window size from existing window.
-    pub fn from_window(window: &Window) -> Self {
+    /// Get the logical size of the playfield.
+    pub fn playfield() -> Self {
         Self {
-            width: window.width(),
-            height: window.height(),
+            width: PLAYFIELD_WIDTH,
+            height: PLAYFIELD_HEIGHT,
         }
     }
 }
 
 fn despawn_everything(
     mut commands: Commands,
-    query: Query<Entity, Or<(With<AnimationTimer>, With<Bullet>, With<Enemy>, With<Star>)>>,
+    query: Query<
+        Entity,
+        Or<(
+            With<Animation>,
+            With<Beam>,
+            With<Bullet>,
+            With<Drone>,
+            With<Enemy>,
+            With<Item>,
+            With<Particle>,
+            With<Star>,
+        )>,
+    >,
 ) {
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
 }
 
-fn setup(mut commands: Commands, windows: Res<Windows>) {
+fn setup(mut commands: Commands) {
     // Set up cameras.
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(CameraShake::default());
     commands.spawn_bundle(UiCameraBundle::default());
 
     // Set up sprite scale.
     commands.insert_resource(SpriteScale::new(1.5));
-
-    // Set up window size.
-    commands.insert_resource({
-        let window = windows.get_primary().unwrap();
-        WindowSize::from_window(window)
-    });
 }
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioChannel};
+
+use crate::game::enemy::{Boss, Enemy};
+use crate::game::level::{CurrentLevel, Level};
+use crate::game::mixer::AudioSettings;
+use crate::game::GameState;
+
+/// Seconds taken to crossfade from one track to the next.
+const CROSSFADE_TIME: f32 = 1.5;
+
+/// Fraction of the music volume kept while paused.
+const PAUSED_VOLUME: f32 = 0.3;
+
+/// Volume change per second while ducking in or out of pause.
+const DUCK_SPEED: f32 = 3.0;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Music::default())
+            .insert_resource(MusicTracks::default())
+            .add_system(choose_track.system().label("choose_track"))
+            .add_system(fade_music.system().after("choose_track"));
+    }
+}
+
+#[derive(Debug)]
+pub struct Music {
+    active: usize,
+    channels: [AudioChannel; 2],
+    duck: f32,
+    progress: f32,
+    track: Option<String>,
+    volumes: [f32; 2],
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        Self {
+            active: 0,
+            channels: [
+                AudioChannel::new("music_a".to_string()),
+                AudioChannel::new("music_b".to_string()),
+            ],
+            duck: 1.0,
+            progress: 1.0,
+            track: None,
+            volumes: [0.0; 2],
+        }
+    }
+}
+
+impl Music {
+    /// Start a looping track on the idle channel and crossfade over to it.
+    fn play(&mut self, server: &AssetServer, audio: &Audio, track: &str) {
+        self.active = 1 - self.active;
+        self.progress = 0.0;
+        self.track = Some(track.to_string());
+
+        let channel = &self.channels[self.active];
+        audio.stop_channel(channel);
+        audio.set_volume_in_channel(0.0, channel);
+        self.volumes[self.active] = 0.0;
+
+        // Either OGG or WAV files work here.
+        let source = server.load(track);
+        audio.play_looped_in_channel(source, channel);
+    }
+}
+
+#[derive(Debug)]
+pub struct MusicTracks {
+    pub boss: String,
+    pub game_over: String,
+    /// Seconds to stay on the game over screen, so its track can be heard.
+    pub game_over_hold: f32,
+}
+
+impl Default for MusicTracks {
+    fn default() -> Self {
+        Self {
+            boss: "music/boss.wav".to_string(),
+            game_over: "music/game_over.wav".to_string(),
+            game_over_hold: CROSSFADE_TIME + 4.0,
+        }
+    }
+}
+
+fn choose_track(
+    server: Res<AssetServer>,
+    audio: Res<Audio>,
+    levels: Res<Vec<Level>>,
+    state: Res<State<GameState>>,
+    tracks: Res<MusicTracks>,
+    mut music: ResMut<Music>,
+    current: Query<&CurrentLevel>,
+    enemies: Query<&Boss, With<Enemy>>,
+) {
+    let boss = enemies.iter().any(|boss| boss.0);
+
+    // Keep the last track once every level is cleared.
+    let track = match state.current() {
+        GameState::GameOver => Some(&tracks.game_over),
+        GameState::Loading => None,
+        _ if boss => Some(&tracks.boss),
+        _ => current
+            .single()
+            .ok()
+            .and_then(|current| current.level)
+            .map(|index| &levels[index].music),
+    };
+
+    if let Some(track) = track {
+        if music.track.as_ref() != Some(track) {
+            music.play(&server, &audio, track);
+        }
+    }
+}
+
+fn fade_music(
+    audio: Res<Audio>,
+    settings: Res<AudioSettings>,
+    state: Res<State<GameState>>,
+    time: Res<Time>,
+    mut music: ResMut<Music>,
+) {
+    let delta = time.delta_seconds();
+
+    // Duck the music while paused.
+    let duck = match state.current() {
+        GameState::Paused => PAUSED_VOLUME,
+        _ => 1.0,
+    };
+    if music.duck < duck {
+        music.duck = (music.duck + DUCK_SPEED * delta).min(duck);
+    } else {
+        music.duck = (music.duck - DUCK_SPEED * delta).max(duck);
+    }
+
+    // Stop the old track once it has faded out.
+    let was_fading = music.progress < 1.0;
+    music.progress = (music.progress + delta / CROSSFADE_TIME).min(1.0);
+    let idle = 1 - music.active;
+    if was_fading && music.progress >= 1.0 {
+        audio.stop_channel(&music.channels[idle]);
+    }
+
+    let volume = settings.music_volume() * music.duck;
+    let volumes = [volume * music.progress, volume * (1.0 - music.progress)];
+    let active = music.active;
+    for (channel, volume) in [active, idle].iter().zip(volumes.iter()) {
+        if (music.volumes[*channel] - volume).abs() > f32::EPSILON {
+            music.volumes[*channel] = *volume;
+            audio.set_volume_in_channel(*volume, &music.channels[*channel]);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::game::assets::GameAssets;
+use crate::game::physics::{Acceleration, Velocity};
+use crate::game::GameState;
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ParticleBurst>()
+            .insert_resource(ParticleBudget { max: 600 })
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(spawn_particles.system().label("spawn_particles"))
+                    .with_system(update_particles.system().after("spawn_particles")),
+            );
+    }
+}
+
+#[derive(Debug)]
+pub struct Emitter {
+    accumulator: f32,
+    effect: ParticleEffect,
+    offset: Vec2,
+    rate: f32,
+}
+
+impl Emitter {
+    /// Emit particles continuously at an offset from the entity.
+    pub fn new(effect: ParticleEffect, offset: Vec2, rate: f32) -> Self {
+        Self {
+            accumulator: 0.0,
+            effect,
+            offset,
+            rate,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Particle {
+    age: f32,
+    effect: ParticleEffect,
+    lifetime: f32,
+}
+
+#[derive(Debug)]
+pub struct ParticleBudget {
+    pub max: usize,
+}
+
+#[derive(Bundle)]
+pub struct ParticleBundle {
+    pub acceleration: Acceleration,
+    pub particle: Particle,
+    #[bundle]
+    pub sprite: SpriteSheetBundle,
+    pub velocity: Velocity,
+}
+
+#[derive(Debug)]
+pub struct ParticleBurst {
+    pub count: u32,
+    pub effect: ParticleEffect,
+    pub position: Vec2,
+}
+
+#[derive(Debug)]
+pub struct ParticleConfig {
+    pub angle: f32,
+    pub colors: (Color, Color),
+    pub cone: f32,
+    pub gravity: Vec2,
+    pub lifetime: (f32, f32),
+    pub sizes: (f32, f32),
+    pub speed: (f32, f32),
+    pub z_index: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ParticleEffect {
+    Debris,
+    EnemyExhaust,
+    Graze,
+    Impact,
+    PlayerExhaust,
+}
+
+impl ParticleEffect {
+    /// Get the settings used to emit and age this effect.
+    pub fn config(self) -> ParticleConfig {
+        match self {
+            Self::Debris => ParticleConfig {
+                angle: 0.0,
+                colors: (Color::rgb(1.0, 0.8, 0.4), Color::rgba(0.6, 0.2, 0.1, 0.0)),
+                cone: 360.0,
+                gravity: Vec2::new(0.0, -0.08),
+                lifetime: (0.4, 0.9),
+                sizes: (4.0, 1.0),
+                speed: (1.0, 4.0),
+                z_index: 4.0,
+            },
+            Self::EnemyExhaust => ParticleConfig {
+                angle: 90.0,
+                colors: (
+                    Color::rgba(1.0, 0.6, 0.2, 0.8),
+                    Color::rgba(1.0, 0.2, 0.1, 0.0),
+                ),
+                cone: 20.0,
+                gravity: Vec2::ZERO,
+                lifetime: (0.15, 0.3),
+                sizes: (3.0, 1.0),
+                speed: (1.0, 2.0),
+                z_index: 1.5,
+            },
+            Self::Graze => ParticleConfig {
+                angle: 0.0,
+                colors: (Color::rgb(1.0, 0.9, 0.6), Color::rgba(1.0, 0.9, 0.6, 0.0)),
+                cone: 360.0,
+                gravity: Vec2::ZERO,
+                lifetime: (0.2, 0.2),
+                sizes: (3.0, 2.0),
+                speed: (1.0, 3.0),
+                z_index: 5.0,
+            },
+            Self::Impact => ParticleConfig {
+                angle: 90.0,
+                colors: (Color::rgb(1.0, 0.8, 0.5), Color::rgba(1.0, 0.5, 0.2, 0.0)),
+                cone: 120.0,
+                gravity: Vec2::ZERO,
+                lifetime: (0.1, 0.2),
+                sizes: (2.0, 1.0),
+                speed: (1.0, 2.5),
+                z_index: 5.0,
+            },
+            Self::PlayerExhaust => ParticleConfig {
+                angle: -90.0,
+                colors: (
+                    Color::rgba(0.6, 0.8, 1.0, 0.8),
+                    Color::rgba(0.2, 0.3, 1.0, 0.0),
+                ),
+                cone: 20.0,
+                gravity: Vec2::ZERO,
+                lifetime: (0.2, 0.35),
+                sizes: (4.0, 1.0),
+                speed: (1.5, 2.5),
+                z_index: 2.5,
+            },
+        }
+    }
+
+    /// Spawn a single particle heading within the cone, turned by a rotation.
+    fn spawn_single(self, assets: &GameAssets, position: Vec2, rotation: Quat) -> ParticleBundle {
+        let mut rng = rand::thread_rng();
+        let config = self.config();
+
+        // Calculate velocity.
+        let velocity = {
+            let spread = config.cone / 2.0;
+            let angle = (config.angle + rng.gen_range(-spread..=spread)).to_radians();
+            let speed = rng.gen_range(config.speed.0..=config.speed.1);
+            let direction = rotation * Vec3::new(angle.cos(), angle.sin(), 0.0);
+            direction.truncate() * speed
+        };
+
+        let (size, _) = config.sizes;
+        ParticleBundle {
+            acceleration: Acceleration(config.gravity),
+            particle: Particle {
+                age: 0.0,
+                effect: self,
+                lifetime: rng.gen_range(config.lifetime.0..=config.lifetime.1),
+            },
+            sprite: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: config.colors.0,
+                    ..Default::default()
+                },
+                texture_atlas: assets.pixel.clone(),
+                transform: Transform {
+                    scale: Vec3::new(size, size, 1.0),
+                    translation: position.extend(config.z_index),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            velocity: Velocity(velocity),
+        }
+    }
+}
+
+/// Blend between two colors, including alpha.
+pub fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::rgba(
+        from.r() + (to.r() - from.r()) * t,
+        from.g() + (to.g() - from.g()) * t,
+        from.b() + (to.b() - from.b()) * t,
+        from.a() + (to.a() - from.a()) * t,
+    )
+}
+
+fn spawn_particles(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    budget: Res<ParticleBudget>,
+    time: Res<Time>,
+    mut events: EventReader<ParticleBurst>,
+    mut emitters: Query<(&mut Emitter, &Transform)>,
+    particles: Query<&Particle>,
+) {
+    // Stop spawning once the budget is used up.
+    let mut alive = particles.iter().count();
+
+    for event in events.iter() {
+        for _ in 0..event.count {
+            if alive >= budget.max {
+                break;
+            }
+
+            commands.spawn_bundle(event.effect.spawn_single(
+                &assets,
+                event.position,
+                Quat::IDENTITY,
+            ));
+            alive += 1;
+        }
+    }
+
+    for (mut emitter, transform) in emitters.iter_mut() {
+        // Carry over partial particles between frames.
+        emitter.accumulator += emitter.rate * time.delta_seconds();
+        let position = transform.translation.truncate()
+            + (transform.rotation * emitter.offset.extend(0.0)).truncate();
+        while emitter.accumulator >= 1.0 {
+            emitter.accumulator -= 1.0;
+            if alive >= budget.max {
+                continue;
+            }
+
+            commands.spawn_bundle(emitter.effect.spawn_single(
+                &assets,
+                position,
+                transform.rotation,
+            ));
+            alive += 1;
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut Particle,
+        &mut TextureAtlasSprite,
+        &mut Transform,
+    )>,
+) {
+    for (entity, mut particle, mut sprite, mut transform) in query.iter_mut() {
+        particle.age += time.delta_seconds();
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // Fade color and size over the particle's lifetime.
+        let config = particle.effect.config();
+        let t = particle.age / particle.lifetime;
+        let size = config.sizes.0 + (config.sizes.1 - config.sizes.0) * t;
+        transform.scale = Vec3::new(size, size, 1.0);
+        sprite.color = lerp_color(config.colors.0, config.colors.1, t);
+    }
+}
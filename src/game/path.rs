@@ -0,0 +1,349 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::game::enemy::{Enemy, Movement};
+use crate::game::physics::Velocity;
+use crate::game::{GameState, WindowSize};
+
+/// Number of samples taken along each spline segment.
+const SEGMENT_SAMPLES: usize = 16;
+
+pub struct PathPlugin;
+
+impl Plugin for PathPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(
+                    follow_paths
+                        .system()
+                        .label("follow_paths")
+                        .before("apply_acceleration"),
+                )
+                .with_system(start_paths.system().before("follow_paths")),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct Leg {
+    pub hold: f32,
+    pub path: SampledPath,
+    pub speed: f32,
+}
+
+impl Leg {
+    /// Create a new leg of a route.
+    pub fn new(path: SampledPath, speed: f32, hold: f32) -> Self {
+        Self { hold, path, speed }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum Path {
+    Bezier(Vec<(f32, f32)>),
+    CatmullRom(Vec<(f32, f32)>),
+    Waypoints(Vec<Waypoint>),
+}
+
+impl Path {
+    /// Check that the path has enough points to follow.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let finite = |(x, y): (f32, f32)| x.is_finite() && y.is_finite();
+        match self {
+            Self::Bezier(points) => {
+                // Each curve after the first reuses the previous end point.
+                anyhow::ensure!(
+                    points.len() >= 4 && (points.len() - 1) % 3 == 0,
+                    "bezier path needs 4, 7, 10, ... points, got {}",
+                    points.len()
+                );
+                anyhow::ensure!(
+                    points.iter().copied().all(finite),
+                    "bezier points must be finite"
+                );
+            }
+            Self::CatmullRom(points) => {
+                anyhow::ensure!(
+                    points.len() >= 2,
+                    "catmull-rom path needs at least 2 points, got {}",
+                    points.len()
+                );
+                anyhow::ensure!(
+                    points.iter().copied().all(finite),
+                    "catmull-rom points must be finite"
+                );
+            }
+            Self::Waypoints(waypoints) => {
+                anyhow::ensure!(!waypoints.is_empty(), "waypoint path needs a waypoint");
+                for waypoint in waypoints.iter() {
+                    anyhow::ensure!(
+                        finite(waypoint.position) && waypoint.hold.is_finite(),
+                        "waypoint positions and holds must be finite"
+                    );
+                    anyhow::ensure!(
+                        waypoint.speed.is_finite() && waypoint.speed > 0.0,
+                        "waypoint speed must be positive"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PathFollower {
+    distance: f32,
+    held: f32,
+    leg: usize,
+    legs: Vec<Leg>,
+}
+
+impl PathFollower {
+    /// Build the legs of a route for an enemy starting at a position.
+    pub fn new(movement: &Movement, start: Vec2, speed: f32, window: &WindowSize) -> Option<Self> {
+        // Paths are authored in coordinates relative to the window.
+        let half = Vec2::new(window.width, window.height) / 2.0;
+        let point = |(x, y): (f32, f32)| Vec2::new(x, y) * half;
+        let line = |from: Vec2, to: Vec2| SampledPath::new(vec![from, to]);
+        let bottom = -1.3 * half.y;
+
+        let legs = match movement {
+            Movement::Circle { radius, turns } => {
+                // Descend, loop around, then carry on downwards.
+                let top = Vec2::new(start.x, 0.3 * half.y);
+                let radius = radius * half.x;
+                let center = top - Vec2::new(0.0, radius);
+                let steps = (turns * 64.0).ceil() as usize;
+                let circle = (0..=steps)
+                    .map(|i| {
+                        let angle = TAU / 4.0 - turns * TAU * i as f32 / steps as f32;
+                        center + Vec2::new(angle.cos(), angle.sin()) * radius
+                    })
+                    .collect();
+
+                vec![
+                    Leg::new(line(start, top), speed, 0.0),
+                    Leg::new(SampledPath::new(circle), speed, 0.0),
+                    Leg::new(line(top, Vec2::new(top.x, bottom)), speed, 0.0),
+                ]
+            }
+            Movement::Dive { .. } | Movement::Down | Movement::Scroll | Movement::Strafe => {
+                return None
+            }
+            Movement::Path(Path::Bezier(points)) => {
+                let points = points.iter().copied().map(point).collect::<Vec<_>>();
+                vec![Leg::new(SampledPath::new(bezier(&points)), speed, 0.0)]
+            }
+            Movement::Path(Path::CatmullRom(points)) => {
+                let points = points.iter().copied().map(point).collect::<Vec<_>>();
+                vec![Leg::new(SampledPath::new(catmull_rom(&points)), speed, 0.0)]
+            }
+            Movement::Path(Path::Waypoints(waypoints)) => {
+                let mut from = start;
+                waypoints
+                    .iter()
+                    .map(|waypoint| {
+                        let to = point(waypoint.position);
+                        let leg = Leg::new(line(from, to), waypoint.speed, waypoint.hold);
+                        from = to;
+                        leg
+                    })
+                    .collect()
+            }
+            Movement::Sine {
+                amplitude,
+                wavelength,
+            } => {
+                // Weave side to side while heading down.
+                let amplitude = amplitude * half.x;
+                let wavelength = wavelength * half.y;
+                let steps = ((start.y - bottom) / 4.0).ceil().max(1.0) as usize;
+                let wave = (0..=steps)
+                    .map(|i| {
+                        let y = start.y + (bottom - start.y) * i as f32 / steps as f32;
+                        let phase = TAU * (start.y - y) / wavelength;
+                        Vec2::new(start.x + amplitude * phase.sin(), y)
+                    })
+                    .collect();
+
+                vec![Leg::new(SampledPath::new(wave), speed, 0.0)]
+            }
+            Movement::Swoop => {
+                // Dive towards the center and climb out the far side.
+                let side = if start.x < 0.0 { 1.0 } else { -1.0 };
+                let points = [
+                    start,
+                    Vec2::new(start.x / 2.0, 0.2 * half.y),
+                    Vec2::new(0.0, -0.2 * half.y),
+                    Vec2::new(side * 0.6 * half.x, 0.1 * half.y),
+                    Vec2::new(side * 1.3 * half.x, 0.9 * half.y),
+                ];
+
+                vec![Leg::new(SampledPath::new(catmull_rom(&points)), speed, 0.0)]
+            }
+        };
+
+        Some(Self {
+            distance: 0.0,
+            held: 0.0,
+            leg: 0,
+            legs,
+        })
+    }
+
+    /// Advance along the route, returning the next position if not finished.
+    pub fn advance(&mut self, seconds: f32) -> Option<Vec2> {
+        let leg = self.legs.get(self.leg)?;
+
+        // Hold at the end of a leg before moving on.
+        if self.distance >= leg.path.length() {
+            self.held += seconds;
+            if self.held < leg.hold {
+                return Some(leg.path.sample(self.distance));
+            }
+
+            self.distance = 0.0;
+            self.held = 0.0;
+            self.leg += 1;
+            return self.advance(0.0);
+        }
+
+        self.distance = (self.distance + leg.speed).min(leg.path.length());
+        Some(leg.path.sample(self.distance))
+    }
+
+    /// Get the velocity to leave with once the route is finished.
+    pub fn exit_velocity(&self) -> Vec2 {
+        self.legs
+            .last()
+            .map_or(Vec2::ZERO, |leg| leg.path.direction() * leg.speed)
+    }
+}
+
+#[derive(Debug)]
+pub struct SampledPath {
+    distances: Vec<f32>,
+    points: Vec<Vec2>,
+}
+
+impl SampledPath {
+    /// Measure the cumulative length along a polyline.
+    pub fn new(points: Vec<Vec2>) -> Self {
+        let mut total = 0.0;
+        let distances = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                if i > 0 {
+                    total += point.distance(points[i - 1]);
+                }
+                total
+            })
+            .collect();
+
+        Self { distances, points }
+    }
+
+    /// Get the direction of the final segment.
+    pub fn direction(&self) -> Vec2 {
+        match self.points.as_slice() {
+            [.., a, b] if a != b => (*b - *a).normalize(),
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// Get the total length of the path.
+    pub fn length(&self) -> f32 {
+        self.distances.last().copied().unwrap_or(0.0)
+    }
+
+    /// Get the position at a distance along the path.
+    pub fn sample(&self, distance: f32) -> Vec2 {
+        // Find the segment containing the distance.
+        let i = match self.distances.binary_search_by(|d| d.total_cmp(&distance)) {
+            Ok(i) => return self.points[i],
+            Err(0) => return self.points.first().copied().unwrap_or(Vec2::ZERO),
+            Err(i) if i == self.points.len() => return *self.points.last().unwrap(),
+            Err(i) => i,
+        };
+
+        let (start, end) = (self.distances[i - 1], self.distances[i]);
+        let t = (distance - start) / (end - start);
+        self.points[i - 1].lerp(self.points[i], t)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Waypoint {
+    pub hold: f32,
+    pub position: (f32, f32),
+    pub speed: f32,
+}
+
+/// Sample a chain of cubic Bezier curves sharing endpoints.
+fn bezier(points: &[Vec2]) -> Vec<Vec2> {
+    let mut samples = Vec::new();
+    for curve in points.windows(4).step_by(3) {
+        let (p0, p1, p2, p3) = (curve[0], curve[1], curve[2], curve[3]);
+        samples.extend((0..SEGMENT_SAMPLES).map(|sample| {
+            let t = sample as f32 / SEGMENT_SAMPLES as f32;
+            let u = 1.0 - t;
+            p0 * u * u * u + p1 * 3.0 * u * u * t + p2 * 3.0 * u * t * t + p3 * t * t * t
+        }));
+    }
+
+    // Finish on the last point of the final curve.
+    let end = points.len().saturating_sub(1) / 3 * 3;
+    samples.extend(points.get(end).copied());
+    samples
+}
+
+/// Sample a Catmull-Rom spline passing through every point.
+fn catmull_rom(points: &[Vec2]) -> Vec<Vec2> {
+    let mut samples = Vec::new();
+    for i in 0..points.len().saturating_sub(1) {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(points.len() - 1)];
+        samples.extend((0..SEGMENT_SAMPLES).map(|sample| {
+            let t = sample as f32 / SEGMENT_SAMPLES as f32;
+            (p1 * 2.0
+                + (p2 - p0) * t
+                + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t * t
+                + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t * t * t)
+                * 0.5
+        }));
+    }
+
+    samples.extend(points.last().copied());
+    samples
+}
+
+fn follow_paths(time: Res<Time>, mut query: Query<(&mut PathFollower, &Transform, &mut Velocity)>) {
+    for (mut follower, transform, mut velocity) in query.iter_mut() {
+        // Move exactly onto the next point, leaving along the final heading.
+        velocity.0 = match follower.advance(time.delta_seconds()) {
+            Some(target) => target - transform.translation.truncate(),
+            None => follower.exit_velocity(),
+        };
+    }
+}
+
+fn start_paths(
+    mut commands: Commands,
+    window: Res<WindowSize>,
+    query: Query<(Entity, &Movement, &Transform, &Velocity), (With<Enemy>, Without<PathFollower>)>,
+) {
+    for (entity, movement, transform, velocity) in query.iter() {
+        let start = transform.translation.truncate();
+        if let Some(follower) = PathFollower::new(movement, start, velocity.0.length(), &window) {
+            commands.entity(entity).insert(follower);
+        }
+    }
+}
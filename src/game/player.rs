@@ -1,30 +1,107 @@
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
-use crate::game::animation::{self, AnimationTimer, GameOverAnimation};
+use crate::game::animation::{self, Animation, AnimationClip, GameOverAnimation, OnFinish};
+use crate::game::assets::GameAssets;
 use crate::game::bullet::FireRate;
+use crate::game::camera::CameraEffect;
 use crate::game::collision::{Hitbox, SpriteSize};
+use crate::game::drone::{Formation, PositionHistory};
 use crate::game::enemy::Health;
-use crate::game::ui::HealthBar;
+use crate::game::graze::{Graze, GrazeMeter};
+use crate::game::input::{Action, Controls};
+use crate::game::mixer::SoundEffect;
+use crate::game::particle::{Emitter, ParticleEffect};
 use crate::game::{GameState, SpriteScale, WindowSize};
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system_set(
-            SystemSet::on_enter(GameState::Playing).with_system(spawn_player.system()),
-        )
-        .add_system_set(
-            SystemSet::on_update(GameState::Playing).with_system(
-                update_health_bar
-                    .system()
-                    .after("collide_with_enemy_bullets"),
-            ),
-        )
-        .add_system_set(
-            SystemSet::on_enter(GameState::GameOver).with_system(explode_player.system()),
-        );
+        app.insert_resource(Ship::Fighter)
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(spawn_player.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(bank_players.system().after("move_player"))
+                    .with_system(join_game.system())
+                    .with_system(
+                        kill_players
+                            .system()
+                            .label("kill_players")
+                            .after("collide_with_enemies")
+                            .after("collide_with_enemy_bullets")
+                            .after("collide_with_enemy_lasers"),
+                    )
+                    .with_system(revive_players.system().after("kill_players"))
+                    .with_system(switch_ships.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(explode_player.system()),
+            );
+    }
+}
+
+#[derive(Debug)]
+pub struct Bombs(pub u32);
+
+#[derive(Debug)]
+pub struct ChargeMeter {
+    charge: f32,
+    capacity: f32,
+}
+
+impl ChargeMeter {
+    /// Create an empty meter that fills in a number of seconds.
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            charge: 0.0,
+            capacity: seconds,
+        }
+    }
+
+    /// Add charge, returning whether the meter just filled up.
+    pub fn charge(&mut self, seconds: f32) -> bool {
+        let was_full = self.is_full();
+        self.charge = (self.charge + seconds).min(self.capacity);
+        !was_full && self.is_full()
+    }
+
+    /// Check if currently charging.
+    pub fn is_charging(&self) -> bool {
+        self.charge > 0.0
+    }
+
+    /// Check if the meter is full.
+    pub fn is_full(&self) -> bool {
+        self.charge >= self.capacity
+    }
+
+    /// Get how full the meter is.
+    pub fn percent(&self) -> f32 {
+        self.charge / self.capacity
+    }
+
+    /// Empty the meter, returning how full it was.
+    pub fn release(&mut self) -> f32 {
+        let percent = self.percent();
+        self.charge = 0.0;
+        percent
+    }
+}
+
+#[derive(Debug)]
+pub struct Downed {
+    timer: Timer,
+}
+
+impl Downed {
+    /// Wait to be revived for a number of seconds.
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, false),
+        }
     }
 }
 
@@ -42,6 +119,11 @@ impl InvulnTimer {
         Self { timer }
     }
 
+    /// Get the time since the timer was last reset.
+    pub fn elapsed_secs(&self) -> f32 {
+        self.timer.elapsed_secs()
+    }
+
     /// Check if the timer is finished.
     pub fn finished(&self) -> bool {
         self.timer.finished()
@@ -59,61 +141,128 @@ impl InvulnTimer {
 }
 
 #[derive(Debug)]
-pub struct Player;
+pub struct Lives(pub u32);
+
+#[derive(Debug)]
+pub struct Player {
+    pub id: usize,
+}
 
 #[derive(Bundle)]
 pub struct PlayerBundle {
+    pub animation: Animation,
+    pub bombs: Bombs,
+    pub controls: Controls,
+    pub emitter: Emitter,
     pub fire_rate: FireRate,
+    pub formation: Formation,
+    pub graze: Graze,
+    pub graze_meter: GrazeMeter,
     pub health: Health,
     pub hitbox: Hitbox,
     pub invuln_timer: InvulnTimer,
+    pub lives: Lives,
     pub player: Player,
+    pub position_history: PositionHistory,
+    pub power: Power,
+    pub score: Score,
+    pub ship: Ship,
     pub speed: Speed,
     #[bundle]
     pub sprite: SpriteSheetBundle,
     pub sprite_size: SpriteSize,
-    pub timer: AnimationTimer,
+    pub steering: Steering,
 }
 
 #[derive(Debug)]
 pub struct PlayerFaction;
 
+#[derive(Debug)]
+pub struct Power {
+    pub level: u32,
+    pub max: u32,
+}
+
+impl Power {
+    /// Start at the lowest power level.
+    pub fn new(max: u32) -> Self {
+        Self { level: 0, max }
+    }
+
+    /// Increase the power level up to the maximum.
+    pub fn increase(&mut self) {
+        self.level = (self.level + 1).min(self.max);
+    }
+}
+
+#[derive(Debug)]
+pub struct Score(pub u32);
+
+#[derive(Clone, Copy, Debug)]
+pub enum Ship {
+    Fighter,
+    Lancer,
+    Striker,
+}
+
+impl Ship {
+    /// Get the charge meter for ships that can charge shots.
+    pub fn charge_meter(self) -> Option<ChargeMeter> {
+        match self {
+            Self::Fighter | Self::Lancer => None,
+            Self::Striker => Some(ChargeMeter::new(1.2)),
+        }
+    }
+
+    /// Get the formation the ship's drones fly in.
+    pub fn formation(self) -> Formation {
+        match self {
+            Self::Fighter | Self::Striker => Formation::Trail,
+            Self::Lancer => Formation::Orbit,
+        }
+    }
+
+    /// Get the ship that comes after this one when switching.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fighter => Self::Lancer,
+            Self::Lancer => Self::Striker,
+            Self::Striker => Self::Fighter,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Speed(pub f32);
 
-fn explode_player(
-    mut commands: Commands,
-    server: Res<AssetServer>,
-    audio: Res<Audio>,
-    mut atlases: ResMut<Assets<TextureAtlas>>,
-    query: Query<(Entity, &Transform), With<Player>>,
-) {
-    let (entity, transform) = query.single().expect("expected a single player");
+#[derive(Debug)]
+pub struct Steering(pub Vec2);
 
-    commands.entity(entity).despawn();
-    commands
-        .spawn_bundle(animation::spawn_explosion(
-            &server,
-            &audio,
-            &mut atlases,
-            *transform,
-        ))
-        .insert(GameOverAnimation);
+/// Set up clips for each row of the sheet, from banking fully left to fully right.
+fn player_animation() -> Animation {
+    let tilt = |first: u32, next: &str| {
+        AnimationClip::once(first, first + 3, 0.03, OnFinish::Switch(next.to_string()))
+    };
+    Animation::new("idle", AnimationClip::looping(8, 11, 0.1))
+        .with_clip("bank_left", AnimationClip::looping(0, 3, 0.1))
+        .with_clip("bank_left_in", tilt(4, "bank_left"))
+        .with_clip("bank_left_out", tilt(4, "idle"))
+        .with_clip("bank_right", AnimationClip::looping(16, 19, 0.1))
+        .with_clip("bank_right_in", tilt(12, "bank_right"))
+        .with_clip("bank_right_out", tilt(12, "idle"))
 }
 
-fn spawn_player(
-    mut commands: Commands,
-    server: Res<AssetServer>,
-    scale: Res<SpriteScale>,
-    mut atlases: ResMut<Assets<TextureAtlas>>,
-    window: Res<WindowSize>,
+/// Spawn a player with the given id.
+fn spawn(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    scale: &SpriteScale,
+    atlases: &Assets<TextureAtlas>,
+    ship: Ship,
+    window: &WindowSize,
+    id: usize,
 ) {
-    // Get texture atlas handle.
-    let texture_atlas = {
-        let asset = server.load("textures/player.png");
-        let atlas = TextureAtlas::from_grid(asset, Vec2::new(50.0, 43.0), 4, 1);
-        atlases.add(atlas)
-    };
+    let texture_atlas = assets.player.clone();
 
     // Get sprite size.
     let sprite_size = {
@@ -122,57 +271,238 @@ fn spawn_player(
         SpriteSize::new(rect.width(), rect.height(), scale.scale)
     };
 
-    commands.spawn_bundle(PlayerBundle {
+    let mut player = commands.spawn_bundle(PlayerBundle {
+        animation: player_animation(),
+        bombs: Bombs(3),
+        controls: Controls::for_player(id),
+        emitter: Emitter::new(
+            ParticleEffect::PlayerExhaust,
+            Vec2::new(0.0, -sprite_size.height / 2.0),
+            40.0,
+        ),
         fire_rate: FireRate::from_seconds(0.18),
+        formation: ship.formation(),
+        graze: Graze::new(36.0, 10),
+        graze_meter: GrazeMeter::new(50),
         health: Health::new(5),
         hitbox: Hitbox { radius: 9.0 },
         invuln_timer: InvulnTimer::new(0.6),
-        player: Player,
+        lives: Lives(2),
+        player: Player { id },
+        position_history: PositionHistory::new(64),
+        power: Power::new(4),
+        score: Score(0),
+        ship,
         speed: Speed(6.0),
         sprite: SpriteSheetBundle {
             texture_atlas,
-            transform: scale.xyz(0.0, -window.height / 4.0, 3.0),
+            transform: scale.xyz(id as f32 * 80.0, -window.height / 4.0, 3.0),
             ..Default::default()
         },
         sprite_size,
-        timer: AnimationTimer::new(0.1),
+        steering: Steering(Vec2::ZERO),
     });
+
+    // Only some ships can charge shots.
+    if let Some(charge_meter) = ship.charge_meter() {
+        player.insert(charge_meter);
+    }
+}
+
+fn bank_players(mut query: Query<(&mut Animation, &Steering), With<Player>>) {
+    for (mut animation, steering) in query.iter_mut() {
+        // Tilt towards the direction of movement, easing back once it stops.
+        let x = steering.0.x;
+        let banking_left = matches!(animation.current(), "bank_left" | "bank_left_in");
+        let banking_right = matches!(animation.current(), "bank_right" | "bank_right_in");
+        if x < 0.0 && !banking_left {
+            animation.play("bank_left_in");
+        } else if x > 0.0 && !banking_right {
+            animation.play("bank_right_in");
+        } else if x == 0.0 && banking_left {
+            animation.play("bank_left_out");
+        } else if x == 0.0 && banking_right {
+            animation.play("bank_right_out");
+        }
+    }
+}
+
+fn explode_player(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut effects: EventWriter<CameraEffect>,
+    query: Query<(Entity, &Transform), With<Player>>,
+) {
+    effects.send(CameraEffect::Shake(1.0));
+    effects.send(CameraEffect::Flash(0.5));
+    for (i, (entity, transform)) in query.iter().enumerate() {
+        commands.entity(entity).despawn();
+        let mut explosion =
+            commands.spawn_bundle(animation::spawn_explosion(&assets, &mut sounds, *transform));
+
+        // Only restart the game once.
+        if i == 0 {
+            explosion.insert(GameOverAnimation);
+        }
+    }
 }
 
-fn update_health_bar(
-    server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    health_bar: Query<&Children, With<HealthBar>>,
-    mut hearts: Query<&mut Handle<ColorMaterial>>,
-    player: Query<&Health, (With<Player>, Changed<Health>)>,
+fn join_game(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    scale: Res<SpriteScale>,
+    atlases: Res<Assets<TextureAtlas>>,
+    ship: Res<Ship>,
+    window: Res<WindowSize>,
+    query: Query<&Player>,
 ) {
-    // Get player health if it has changed.
-    let health = match player.iter().next() {
-        Some(health) => health,
-        None => return,
-    };
+    // Let the second player join by pressing fire.
+    let id = 1;
+    if query.iter().any(|player| player.id == id) {
+        return;
+    }
 
-    // Get material handles.
-    let full_heart = {
-        let asset = server.load("textures/ui/heart.png");
-        materials.add(asset.into())
-    };
-    let empty_heart = {
-        let asset = server.load("textures/ui/heart_empty.png");
-        materials.add(asset.into())
-    };
+    if Controls::for_player(id).just_pressed(Action::Fire, &keys, &buttons) {
+        spawn(&mut commands, &assets, &scale, &atlases, *ship, &window, id);
+    }
+}
 
-    for (i, heart) in health_bar
-        .single()
-        .expect("expected a single health bar")
-        .iter()
-        .enumerate()
+fn kill_players(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    mut sounds: EventWriter<SoundEffect>,
+    mut state: ResMut<State<GameState>>,
+    mut query: Query<
+        (
+            Entity,
+            Option<&Downed>,
+            &mut Health,
+            &mut InvulnTimer,
+            &mut Lives,
+            &mut TextureAtlasSprite,
+            &Transform,
+        ),
+        With<Player>,
+    >,
+) {
+    let mut players = 0;
+    let mut standing = 0;
+    for (entity, downed, mut health, mut invuln_timer, mut lives, mut sprite, transform) in
+        query.iter_mut()
+    {
+        // Skip players that are still alive or already down.
+        players += 1;
+        if downed.is_some() {
+            continue;
+        } else if health.current > 0 {
+            standing += 1;
+            continue;
+        }
+
+        commands.spawn_bundle(animation::spawn_explosion(&assets, &mut sounds, *transform));
+
+        if lives.0 > 0 {
+            // Use up a life to recover in place.
+            lives.0 -= 1;
+            health.current = health.max;
+            invuln_timer.reset();
+            standing += 1;
+        } else {
+            // Wait for a teammate to revive.
+            commands.entity(entity).insert(Downed::new(2.0));
+            sprite.color.set_a(0.3);
+        }
+    }
+
+    // End the game once every player is down.
+    if players > 0 && standing == 0 {
+        state.set(GameState::GameOver).unwrap();
+    }
+}
+
+fn revive_players(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut downed: Query<
+        (
+            Entity,
+            &mut Downed,
+            &mut Health,
+            &mut InvulnTimer,
+            &mut TextureAtlasSprite,
+            &Transform,
+        ),
+        With<Player>,
+    >,
+    standing: Query<&Transform, (With<Player>, Without<Downed>)>,
+) {
+    for (entity, mut downed, mut health, mut invuln_timer, mut sprite, transform) in
+        downed.iter_mut()
     {
-        let mut material = hearts.get_mut(*heart).expect("expected a single heart");
-        *material = if i < health.current as usize {
-            full_heart.clone()
+        // Only make progress while a teammate stays close.
+        let position = transform.translation.truncate();
+        if standing
+            .iter()
+            .any(|t| t.translation.truncate().distance(position) < 80.0)
+        {
+            downed.timer.tick(time.delta());
         } else {
-            empty_heart.clone()
-        };
+            downed.timer.reset();
+        }
+
+        if downed.timer.finished() {
+            commands.entity(entity).remove::<Downed>();
+            health.current = (health.max / 2).max(1);
+            invuln_timer.reset();
+            sprite.color.set_a(1.0);
+        }
+    }
+}
+
+fn spawn_player(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    scale: Res<SpriteScale>,
+    atlases: Res<Assets<TextureAtlas>>,
+    ship: Res<Ship>,
+    window: Res<WindowSize>,
+) {
+    spawn(&mut commands, &assets, &scale, &atlases, *ship, &window, 0);
+}
+
+fn switch_ships(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut selected: ResMut<Ship>,
+    mut query: Query<
+        (
+            Entity,
+            &Controls,
+            &mut Formation,
+            &mut Ship,
+            &mut TextureAtlasSprite,
+        ),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for (entity, controls, mut formation, mut ship, mut sprite) in query.iter_mut() {
+        if controls.just_pressed(Action::SwitchShip, &keys, &buttons) {
+            *ship = ship.next();
+            *formation = ship.formation();
+
+            // Swap the charge meter, dropping any charge in progress.
+            match ship.charge_meter() {
+                Some(charge_meter) => commands.entity(entity).insert(charge_meter),
+                None => commands.entity(entity).remove::<ChargeMeter>(),
+            };
+            sprite.color = Color::WHITE;
+
+            // Remember the choice for the next player to spawn.
+            *selected = *ship;
+        }
     }
 }
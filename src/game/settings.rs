@@ -0,0 +1,82 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::camera::CameraSettings;
+use crate::game::mixer::AudioSettings;
+
+/// File the settings are loaded from and saved to.
+const SETTINGS_PATH: &str = "settings.ron";
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let (settings, error) = match Settings::load() {
+            Ok(settings) => (settings, None),
+            Err(err) => {
+                let error = format!("{} is invalid: {}", SETTINGS_PATH, err);
+                (Settings::default(), Some(error))
+            }
+        };
+        app.insert_resource(settings.audio)
+            .insert_resource(settings.camera)
+            .insert_resource(SettingsError(error))
+            .add_system(save_settings.system());
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Settings {
+    audio: AudioSettings,
+    camera: CameraSettings,
+}
+
+impl Settings {
+    /// Load settings from disk, falling back to defaults if there is no file yet.
+    fn load() -> anyhow::Result<Self> {
+        let text = match fs::read_to_string(SETTINGS_PATH) {
+            Ok(text) => text,
+            Err(_) => return Ok(Self::default()),
+        };
+
+        Ok(ron::de::from_str(&text)?)
+    }
+
+    /// Save settings to disk.
+    fn save(&self) -> anyhow::Result<()> {
+        let text = ron::ser::to_string_pretty(self, Default::default())?;
+        fs::write(SETTINGS_PATH, text)?;
+        Ok(())
+    }
+}
+
+/// Why the settings file couldn't be read, reported on the loading screen.
+#[derive(Debug)]
+pub struct SettingsError(pub Option<String>);
+
+fn save_settings(
+    audio: Res<AudioSettings>,
+    camera: Res<CameraSettings>,
+    error: Res<SettingsError>,
+) {
+    // Skip the first frame, when the settings were only just loaded.
+    if audio.is_added() || !(audio.is_changed() || camera.is_changed()) {
+        return;
+    }
+
+    // Leave an invalid file alone rather than replacing it with defaults.
+    if error.0.is_some() {
+        return;
+    }
+
+    let settings = Settings {
+        audio: audio.clone(),
+        camera: camera.clone(),
+    };
+    if let Err(err) = settings.save() {
+        warn!("failed to save {}: {}", SETTINGS_PATH, err);
+    }
+}
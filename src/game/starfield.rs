@@ -1,19 +1,57 @@
 use bevy::prelude::*;
 use rand::prelude::*;
 
+use crate::game::background::Fade;
 use crate::game::physics::Velocity;
 use crate::game::{GameState, WindowSize};
 
+/// Change in scroll speed per second while easing towards a new target.
+const SCROLL_EASE: f32 = 1.5;
+
 pub struct StarfieldPlugin;
 
 impl Plugin for StarfieldPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system_set(SystemSet::on_enter(GameState::Playing).with_system(setup.system()));
+        app.insert_resource(ScrollSpeed::new(2.0))
+            .insert_resource(StarfieldConfig::default())
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(setup.system()))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(ease_scroll_speed.system().label("ease_scroll_speed"))
+                    .with_system(scroll_stars.system().after("ease_scroll_speed")),
+            );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NebulaLayer {
+    pub scale: f32,
+    pub speed: f32,
+    pub texture: String,
+    pub tint: Color,
+    pub z_index: f32,
+}
+
+#[derive(Debug)]
+pub struct ScrollSpeed {
+    pub current: f32,
+    pub target: f32,
+}
+
+impl ScrollSpeed {
+    /// Start scrolling at a steady speed.
+    pub fn new(speed: f32) -> Self {
+        Self {
+            current: speed,
+            target: speed,
+        }
     }
 }
 
 #[derive(Debug)]
-pub struct Star;
+pub struct Star {
+    pub speed: f32,
+}
 
 #[derive(Bundle)]
 pub struct StarBundle {
@@ -23,57 +61,231 @@ pub struct StarBundle {
     pub velocity: Velocity,
 }
 
-fn setup(
-    mut commands: Commands,
-    server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    window: Res<WindowSize>,
+#[derive(Clone, Debug)]
+pub struct StarLayer {
+    pub count: u32,
+    pub speed: (f32, f32),
+    pub textures: Vec<(String, u32)>,
+    pub tint: Color,
+    pub z_index: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct StarfieldConfig {
+    pub layers: Vec<StarLayer>,
+    pub nebulae: Vec<NebulaLayer>,
+}
+
+impl Default for StarfieldConfig {
+    fn default() -> Self {
+        let textures = |names: &[(&str, u32)]| {
+            names
+                .iter()
+                .map(|(name, weight)| (format!("textures/starfield/{}.png", name), *weight))
+                .collect()
+        };
+
+        Self {
+            layers: vec![
+                StarLayer {
+                    count: 100,
+                    speed: (0.5, 1.0),
+                    textures: textures(&[("small", 1)]),
+                    tint: Color::rgb(0.5, 0.5, 0.65),
+                    z_index: 0.1,
+                },
+                StarLayer {
+                    count: 70,
+                    speed: (1.25, 2.5),
+                    textures: textures(&[("small", 3), ("medium", 2)]),
+                    tint: Color::rgb(0.8, 0.8, 0.9),
+                    z_index: 0.2,
+                },
+                StarLayer {
+                    count: 30,
+                    speed: (3.0, 4.5),
+                    textures: textures(&[("medium", 1), ("large", 1)]),
+                    tint: Color::WHITE,
+                    z_index: 0.3,
+                },
+            ],
+            nebulae: vec![NebulaLayer {
+                scale: 2.5,
+                speed: 0.15,
+                texture: "textures/starfield/nebula.png".to_string(),
+                tint: Color::rgba(1.0, 1.0, 1.0, 0.6),
+                z_index: 0.0,
+            }],
+        }
+    }
+}
+
+impl StarfieldConfig {
+    /// Get the paths of every star and nebula texture.
+    pub fn textures(&self) -> Vec<&str> {
+        let stars = self.layers.iter().flat_map(|layer| layer.textures.iter());
+        stars
+            .map(|(texture, _)| texture.as_str())
+            .chain(self.nebulae.iter().map(|nebula| nebula.texture.as_str()))
+            .collect()
+    }
+
+    /// Scale the number of stars in every layer.
+    pub fn with_density(mut self, density: f32) -> Self {
+        for layer in self.layers.iter_mut() {
+            layer.count = (layer.count as f32 * density).round() as u32;
+        }
+        self
+    }
+
+    /// Recolor every nebula.
+    pub fn with_nebula_tint(mut self, tint: Color) -> Self {
+        for nebula in self.nebulae.iter_mut() {
+            nebula.tint = tint;
+        }
+        self
+    }
+}
+
+/// Create a material, starting transparent if it is going to fade in.
+///
+/// Every spawn gets its own material rather than a cached one, since fades change its color while
+/// the outgoing theme's copy of the same texture fades the other way.
+fn tinted_material(
+    materials: &mut Assets<ColorMaterial>,
+    texture: Handle<Texture>,
+    tint: Color,
+    fade: Option<f32>,
+) -> Handle<ColorMaterial> {
+    let mut color = tint;
+    if fade.is_some() {
+        color.set_a(0.0);
+    }
+    materials.add(ColorMaterial::modulated_texture(texture, color))
+}
+
+/// Spawn every nebula and star layer in a config, optionally fading them in.
+pub fn spawn_starfield(
+    commands: &mut Commands,
+    server: &AssetServer,
+    materials: &mut Assets<ColorMaterial>,
+    config: &StarfieldConfig,
+    scroll_speed: &ScrollSpeed,
+    window: &WindowSize,
+    fade: Option<f32>,
 ) {
-    // Get material handles.
-    let urls = vec![
-        ("textures/starfield/small.png", 3),
-        ("textures/starfield/medium.png", 2),
-        ("textures/starfield/large.png", 1),
-    ];
-    let materials: Vec<_> = urls
-        .iter()
-        .map(|(url, weight)| {
-            let asset = server.load(*url);
-            (materials.add(asset.into()), weight)
-        })
-        .collect();
-
-    // Create starfield.
     let mut rng = rand::thread_rng();
-    for _ in 0..200 {
-        // Get material handle.
-        let material = materials
-            .choose_weighted(&mut rng, |e| e.1)
-            .unwrap()
-            .0
-            .clone();
-
-        // Calculate transform.
+
+    // Create nebulae, drifting like very distant stars.
+    for nebula in config.nebulae.iter() {
+        let material = {
+            let asset = server.load(nebula.texture.as_str());
+            tinted_material(materials, asset, nebula.tint, fade)
+        };
+
         let transform = {
             let x = rng.gen_range((-window.width / 2.0)..(window.width / 2.0));
             let y = rng.gen_range((-window.height / 2.0)..(window.height / 2.0));
-            Transform::from_translation(Vec3::new(x, y, 0.0))
-        };
-
-        // Calculate velocity.
-        let velocity = {
-            let speed = rng.gen_range(1.0..9.0);
-            Velocity(Vec2::new(0.0, -speed))
+            Transform {
+                translation: Vec3::new(x, y, nebula.z_index),
+                scale: Vec3::new(nebula.scale, nebula.scale, 1.0),
+                ..Default::default()
+            }
         };
 
-        commands.spawn_bundle(StarBundle {
-            star: Star,
+        let mut entity = commands.spawn_bundle(StarBundle {
+            star: Star {
+                speed: nebula.speed,
+            },
             sprite: SpriteBundle {
                 material,
                 transform,
                 ..Default::default()
             },
-            velocity,
+            velocity: Velocity(Vec2::new(0.0, -nebula.speed * scroll_speed.current)),
         });
+        if let Some(seconds) = fade {
+            entity.insert(Fade::fade_in(nebula.tint, seconds));
+        }
+    }
+
+    // Create star layers.
+    for layer in config.layers.iter() {
+        // Get material handles.
+        let handles: Vec<_> = layer
+            .textures
+            .iter()
+            .map(|(url, weight)| {
+                let asset = server.load(url.as_str());
+                (tinted_material(materials, asset, layer.tint, fade), *weight)
+            })
+            .collect();
+
+        for _ in 0..layer.count {
+            // Get material handle.
+            let material = handles
+                .choose_weighted(&mut rng, |e| e.1)
+                .unwrap()
+                .0
+                .clone();
+
+            // Calculate transform.
+            let transform = {
+                let x = rng.gen_range((-window.width / 2.0)..(window.width / 2.0));
+                let y = rng.gen_range((-window.height / 2.0)..(window.height / 2.0));
+                Transform::from_translation(Vec3::new(x, y, layer.z_index))
+            };
+
+            // Pick a speed within the layer, relative to the scroll speed.
+            let speed = rng.gen_range(layer.speed.0..=layer.speed.1);
+
+            let mut entity = commands.spawn_bundle(StarBundle {
+                star: Star { speed },
+                sprite: SpriteBundle {
+                    material,
+                    transform,
+                    ..Default::default()
+                },
+                velocity: Velocity(Vec2::new(0.0, -speed * scroll_speed.current)),
+            });
+            if let Some(seconds) = fade {
+                entity.insert(Fade::fade_in(layer.tint, seconds));
+            }
+        }
+    }
+}
+
+fn ease_scroll_speed(time: Res<Time>, mut scroll_speed: ResMut<ScrollSpeed>) {
+    let step = SCROLL_EASE * time.delta_seconds();
+    let difference = scroll_speed.target - scroll_speed.current;
+    if difference.abs() <= step {
+        scroll_speed.current = scroll_speed.target;
+    } else {
+        scroll_speed.current += step * difference.signum();
     }
 }
+
+fn scroll_stars(scroll_speed: Res<ScrollSpeed>, mut query: Query<(&Star, &mut Velocity)>) {
+    for (star, mut velocity) in query.iter_mut() {
+        velocity.0 = Vec2::new(0.0, -star.speed * scroll_speed.current);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<StarfieldConfig>,
+    scroll_speed: Res<ScrollSpeed>,
+    window: Res<WindowSize>,
+) {
+    spawn_starfield(
+        &mut commands,
+        &server,
+        &mut materials,
+        &config,
+        &scroll_speed,
+        &window,
+        None,
+    );
+}
@@ -1,65 +1,401 @@
 use bevy::prelude::*;
 
+use crate::game::assets::GameAssets;
+use crate::game::display::{Viewport, PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH};
+use crate::game::enemy::Health;
+use crate::game::graze::Graze;
+use crate::game::level::CurrentLevel;
+use crate::game::player::{Bombs, Lives, Player, Power, Score};
+use crate::game::GameState;
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(setup.system());
+        app.insert_resource(HighScore(0))
+            .insert_resource(HudLayout::default())
+            .add_system(build_hud.system().after("scale_playfield"))
+            .add_system(toggle_hud_layout.system())
+            .add_system(update_health_bars.system())
+            .add_system(update_high_score.system().label("update_high_score"))
+            .add_system(update_hud_text.system().after("update_high_score"));
+    }
+}
+
+#[derive(Debug)]
+pub struct HealthBar {
+    pub player: usize,
+}
+
+#[derive(Debug)]
+pub struct HighScore(pub u32);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HudItem {
+    Bombs(usize),
+    Graze(usize),
+    Health(usize),
+    HighScore,
+    Lives(usize),
+    Power(usize),
+    Score(usize),
+    Stage,
+}
+
+impl HudItem {
+    /// Get the player this item describes, if any.
+    fn player(self) -> Option<usize> {
+        match self {
+            Self::Bombs(player)
+            | Self::Graze(player)
+            | Self::Health(player)
+            | Self::Lives(player)
+            | Self::Power(player)
+            | Self::Score(player) => Some(player),
+            Self::HighScore | Self::Stage => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HudLayout {
+    /// Width of each side panel in playfield units, or zero to draw over the playfield.
+    pub panel_width: f32,
+    pub panels: [Vec<HudItem>; 2],
+}
+
+impl Default for HudLayout {
+    fn default() -> Self {
+        Self::overlay()
+    }
+}
+
+impl HudLayout {
+    /// Small panels in the bottom corners of the playfield.
+    pub fn overlay() -> Self {
+        let panel = |player| {
+            vec![
+                HudItem::Score(player),
+                HudItem::Graze(player),
+                HudItem::Lives(player),
+                HudItem::Health(player),
+            ]
+        };
+
+        Self {
+            panel_width: 0.0,
+            panels: [panel(0), panel(1)],
+        }
+    }
+
+    /// Full panels either side of the playfield, like classic vertical shooters.
+    pub fn side_panels() -> Self {
+        let panel = |shared, player| {
+            vec![
+                shared,
+                HudItem::Score(player),
+                HudItem::Lives(player),
+                HudItem::Health(player),
+                HudItem::Bombs(player),
+                HudItem::Power(player),
+                HudItem::Graze(player),
+            ]
+        };
+
+        Self {
+            panel_width: 280.0,
+            panels: [panel(HudItem::HighScore, 0), panel(HudItem::Stage, 1)],
+        }
     }
 }
 
 #[derive(Debug)]
-pub struct HealthBar;
+pub struct HudRoot;
+
+#[derive(Debug)]
+pub struct HudText {
+    pub item: HudItem,
+}
 
-fn setup(
+#[derive(Debug, Default)]
+struct PlayerStats {
+    bombs: u32,
+    graze: u32,
+    lives: u32,
+    power: u32,
+    score: u32,
+}
+
+/// Get the text shown for a HUD item.
+fn hud_text(
+    item: HudItem,
+    stats: Option<&PlayerStats>,
+    high_score: u32,
+    stage: Option<usize>,
+) -> String {
+    // Show zeroes for the first player, and leave other panels empty until they join.
+    let empty = PlayerStats::default();
+    let stats = match (item.player(), stats) {
+        (Some(0), None) => Some(&empty),
+        (_, stats) => stats,
+    };
+
+    match (item, stats) {
+        (HudItem::Bombs(_), Some(stats)) => format!("Bombs {}", stats.bombs),
+        (HudItem::Graze(_), Some(stats)) => format!("Graze {}", stats.graze),
+        (HudItem::HighScore, _) => format!("Hi-Score {}", high_score),
+        (HudItem::Lives(_), Some(stats)) => format!("Lives {}", stats.lives),
+        (HudItem::Power(_), Some(stats)) => format!("Power {}", stats.power),
+        (HudItem::Score(_), Some(stats)) => format!("Score {}", stats.score),
+        (HudItem::Score(_), None) => "Press fire to join".to_string(),
+        (HudItem::Stage, _) => match stage {
+            Some(index) => format!("Stage {}", index + 1),
+            None => "All clear".to_string(),
+        },
+        _ => String::new(),
+    }
+}
+
+/// Spawn the rows of a panel.
+fn spawn_items(parent: &mut ChildBuilder, assets: &GameAssets, items: &[HudItem], scale: f32) {
+    let text_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 24.0 * scale,
+        color: Color::WHITE,
+    };
+
+    for item in items.iter().copied() {
+        let player = match item {
+            HudItem::Health(player) => player,
+            _ => {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section("", text_style.clone(), Default::default()),
+                        ..Default::default()
+                    })
+                    .insert(HudText { item });
+                continue;
+            }
+        };
+
+        parent
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    margin: Rect {
+                        top: Val::Px(8.0 * scale),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                material: assets.none.clone(),
+                ..Default::default()
+            })
+            .insert(HealthBar { player })
+            .with_children(|parent| {
+                for _ in 0..5 {
+                    parent.spawn_bundle(ImageBundle {
+                        style: Style {
+                            margin: Rect {
+                                left: Val::Px(10.0 * scale),
+                                right: Val::Px(10.0 * scale),
+                                ..Default::default()
+                            },
+                            size: Size::new(Val::Px(24.0 * scale), Val::Px(21.0 * scale)),
+                            ..Default::default()
+                        },
+                        material: assets.heart_empty.clone(),
+                        transform: Transform::from_scale(Vec3::new(1.5, 1.5, 0.0)),
+                        ..Default::default()
+                    });
+                }
+            });
+    }
+}
+
+fn build_hud(
     mut commands: Commands,
-    server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    assets: Res<GameAssets>,
+    layout: Res<HudLayout>,
+    state: Res<State<GameState>>,
+    viewport: Res<Viewport>,
+    mut built: Local<bool>,
+    roots: Query<Entity, With<HudRoot>>,
 ) {
-    // Get material handle.
-    let material = {
-        let asset = server.load("textures/ui/heart.png");
-        materials.add(asset.into())
-    };
+    // Wait for loading to finish, so the HUD isn't drawn over the loading screen.
+    if *state.current() == GameState::Loading {
+        return;
+    }
+    if *built && !layout.is_changed() && !viewport.is_changed() {
+        return;
+    }
+    *built = true;
+
+    // Rebuild from scratch, since every value is refreshed each frame anyway.
+    for root in roots.iter() {
+        commands.entity(root).despawn_recursive();
+    }
+
+    let scale = viewport.scale;
+    let playfield = Size::new(
+        Val::Px(PLAYFIELD_WIDTH * scale),
+        Val::Px(PLAYFIELD_HEIGHT * scale),
+    );
 
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
                 size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
                 ..Default::default()
             },
-            material: materials.add(Color::NONE.into()),
+            material: assets.none.clone(),
             ..Default::default()
         })
+        .insert(HudRoot)
         .with_children(|parent| {
+            if layout.panel_width > 0.0 {
+                // Spawn a panel either side of the playfield.
+                let panel_style = Style {
+                    size: Size::new(Val::Px(layout.panel_width * scale), playfield.height),
+                    align_items: AlignItems::FlexStart,
+                    flex_direction: FlexDirection::ColumnReverse,
+                    justify_content: JustifyContent::FlexEnd,
+                    padding: Rect::all(Val::Px(20.0 * scale)),
+                    ..Default::default()
+                };
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: panel_style.clone(),
+                        material: assets.panel.clone(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| spawn_items(parent, &assets, &layout.panels[0], scale));
+                parent.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: playfield,
+                        ..Default::default()
+                    },
+                    material: assets.none.clone(),
+                    ..Default::default()
+                });
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: panel_style,
+                        material: assets.panel.clone(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| spawn_items(parent, &assets, &layout.panels[1], scale));
+                return;
+            }
+
+            // Spawn a panel in each bottom corner of the playfield.
             parent
                 .spawn_bundle(NodeBundle {
                     style: Style {
-                        align_content: AlignContent::FlexEnd,
+                        size: playfield,
+                        justify_content: JustifyContent::SpaceBetween,
                         ..Default::default()
                     },
-                    material: materials.add(Color::NONE.into()),
+                    material: assets.none.clone(),
                     ..Default::default()
                 })
-                .insert(HealthBar)
                 .with_children(|parent| {
-                    for _ in 0..5 {
-                        parent.spawn_bundle(ImageBundle {
-                            style: Style {
-                                margin: Rect {
-                                    left: Val::Px(20.0),
-                                    bottom: Val::Px(20.0),
+                    for (items, align_items) in layout
+                        .panels
+                        .iter()
+                        .zip([AlignItems::FlexStart, AlignItems::FlexEnd].iter().copied())
+                    {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    align_self: AlignSelf::FlexStart,
+                                    align_items,
+                                    flex_direction: FlexDirection::ColumnReverse,
+                                    margin: Rect::all(Val::Px(20.0 * scale)),
                                     ..Default::default()
                                 },
-                                size: Size::new(Val::Px(24.0), Val::Px(21.0)),
+                                material: assets.none.clone(),
                                 ..Default::default()
-                            },
-                            material: material.clone(),
-                            transform: Transform::from_scale(Vec3::new(1.5, 1.5, 0.0)),
-                            ..Default::default()
-                        });
+                            })
+                            .with_children(|parent| spawn_items(parent, &assets, items, scale));
                     }
                 });
         });
 }
+
+fn toggle_hud_layout(mut layout: ResMut<HudLayout>, mut keys: ResMut<Input<KeyCode>>) {
+    // Switch layouts when F9 is pressed.
+    if keys.just_pressed(KeyCode::F9) {
+        *layout = if layout.panel_width > 0.0 {
+            HudLayout::overlay()
+        } else {
+            HudLayout::side_panels()
+        };
+        keys.reset(KeyCode::F9);
+    }
+}
+
+fn update_health_bars(
+    assets: Res<GameAssets>,
+    health_bars: Query<(&HealthBar, &Children)>,
+    players: Query<(&Health, &Player)>,
+    mut hearts: Query<&mut Handle<ColorMaterial>>,
+) {
+    for (bar, children) in health_bars.iter() {
+        // Show empty hearts for players who haven't joined.
+        let health = players
+            .iter()
+            .find(|(_, player)| player.id == bar.player)
+            .map_or(0, |(health, _)| health.current as usize);
+
+        for (i, heart) in children.iter().enumerate() {
+            let mut material = hearts.get_mut(*heart).expect("expected a single heart");
+            let target = if i < health {
+                &assets.heart
+            } else {
+                &assets.heart_empty
+            };
+            if *material != *target {
+                *material = target.clone();
+            }
+        }
+    }
+}
+
+fn update_high_score(mut high_score: ResMut<HighScore>, players: Query<&Score, With<Player>>) {
+    if let Some(best) = players.iter().map(|score| score.0).max() {
+        if best > high_score.0 {
+            high_score.0 = best;
+        }
+    }
+}
+
+fn update_hud_text(
+    high_score: Res<HighScore>,
+    levels: Query<&CurrentLevel>,
+    players: Query<(&Player, &Bombs, &Graze, &Lives, &Power, &Score)>,
+    mut texts: Query<(&mut Text, &HudText)>,
+) {
+    let stage = levels.single().ok().and_then(|current| current.level);
+
+    for (mut text, label) in texts.iter_mut() {
+        let stats = label.item.player().and_then(|id| {
+            players.iter().find(|(player, ..)| player.id == id).map(
+                |(_, bombs, graze, lives, power, score)| PlayerStats {
+                    bombs: bombs.0,
+                    graze: graze.count,
+                    lives: lives.0,
+                    power: power.level,
+                    score: score.0,
+                },
+            )
+        });
+
+        // Only touch text that changed, to avoid relaying out the HUD every frame.
+        let value = hud_text(label.item, stats.as_ref(), high_score.0, stage);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy_kira_audio::AudioPlugin;
 
 use game::GamePlugin;
 
@@ -11,10 +12,10 @@ fn main() {
             width: 800.0,
             height: 960.0,
             title: "Zenith".to_string(),
-            resizable: false,
             ..Default::default()
         })
         .add_plugins(DefaultPlugins)
+        .add_plugin(AudioPlugin)
         .add_plugin(GamePlugin)
         .run();
 }